@@ -0,0 +1,327 @@
+use crate::{
+    process::{self, Process, Transfer},
+    thermo::{EquationOfState, Scalar},
+    trace::TracePoint,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CycleSummary {
+    pub net_work: Scalar,             // J, done by the gas
+    pub heat_absorbed: Scalar,        // J
+    pub heat_rejected: Scalar,        // J, counted positive
+    pub efficiency: Scalar,           // net work over heat absorbed
+    pub carnot_efficiency: Scalar,    // between the extreme tempuratures of the cycle
+    pub min_tempurature: Scalar,      // K
+    pub max_tempurature: Scalar,      // K
+    pub absorbed_tempurature: Scalar, // K, mean tempurature heat came in at, weighted by heat
+    pub rejected_tempurature: Scalar, // K, the same for heat going out
+}
+
+impl CycleSummary {
+    // Counter-clockwise on the PV plot, so work goes in and heat is pumped
+    // from the cold side to the hot side, as in a refrigerator or heat pump.
+    pub fn is_reversed(&self) -> bool {
+        self.net_work < 0.
+    }
+
+    // heat taken from the cold side per unit of work put in
+    pub fn cooling_cop(&self) -> Scalar {
+        self.heat_absorbed / -self.net_work
+    }
+
+    // heat delivered to the hot side per unit of work put in
+    pub fn heating_cop(&self) -> Scalar {
+        self.heat_rejected / -self.net_work
+    }
+
+    // the best any reversed cycle between the extreme tempuratures can do
+    pub fn carnot_cooling_cop(&self) -> Scalar {
+        self.min_tempurature / (self.max_tempurature - self.min_tempurature)
+    }
+
+    pub fn carnot_heating_cop(&self) -> Scalar {
+        self.max_tempurature / (self.max_tempurature - self.min_tempurature)
+    }
+}
+
+// True once the trace has left its first state and come back to within the
+// tolerances of it.
+pub fn is_closed(
+    points: &[TracePoint],
+    volume_tolerance: Scalar,
+    pressure_tolerance: Scalar,
+) -> bool {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return false;
+    };
+    let distance = |point: &TracePoint| {
+        ((point.volume - first.volume) / volume_tolerance)
+            .hypot((point.pressure - first.pressure) / pressure_tolerance)
+    };
+    distance(last) < 1. && points.iter().any(|point| distance(point) > 2.)
+}
+
+// Adds up a closed trace, including the short straight segment that joins its
+// last state back to its first.
+pub fn summarize(points: &[TracePoint], gas_model: &dyn EquationOfState) -> CycleSummary {
+    let mut net_work = 0.;
+    let mut heat_absorbed = 0.;
+    let mut heat_rejected = 0.;
+    // heat times the tempurature it flowed at, for the weighted means
+    let mut absorbed_moment = 0.;
+    let mut rejected_moment = 0.;
+    let mut add_segment = |transfer: Transfer, tempurature: Scalar| {
+        net_work += transfer.work;
+        heat_absorbed += transfer.heat_absorbed;
+        heat_rejected += transfer.heat_rejected();
+        absorbed_moment += transfer.heat_absorbed * tempurature;
+        rejected_moment += transfer.heat_rejected() * tempurature;
+    };
+    for point in points.iter().skip(1) {
+        add_segment(
+            Transfer {
+                work: point.work,
+                heat: point.heat,
+                heat_absorbed: point.heat_absorbed,
+            },
+            gas_model.get_tempurature(point.volume, point.pressure),
+        );
+    }
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        add_segment(
+            process::get_transfer(
+                gas_model,
+                Process::Line,
+                last.volume,
+                last.pressure,
+                first.volume,
+                first.pressure,
+            ),
+            gas_model.get_tempurature(first.volume, first.pressure),
+        );
+    }
+
+    let mut min_tempurature = Scalar::INFINITY;
+    let mut max_tempurature = Scalar::NEG_INFINITY;
+    for point in points {
+        let tempurature = gas_model.get_tempurature(point.volume, point.pressure);
+        min_tempurature = min_tempurature.min(tempurature);
+        max_tempurature = max_tempurature.max(tempurature);
+    }
+
+    CycleSummary {
+        net_work,
+        heat_absorbed,
+        heat_rejected,
+        efficiency: if heat_absorbed > 0. {
+            net_work / heat_absorbed
+        } else {
+            0.
+        },
+        carnot_efficiency: 1. - min_tempurature / max_tempurature,
+        min_tempurature,
+        max_tempurature,
+        absorbed_tempurature: absorbed_moment / heat_absorbed,
+        rejected_tempurature: rejected_moment / heat_rejected,
+    }
+}
+
+// The trace with the straight closing segment added as a last point back on
+// the first state, `duration` seconds after the one before it.
+pub fn close(
+    points: &[TracePoint],
+    gas_model: &dyn EquationOfState,
+    duration: Scalar,
+) -> Vec<TracePoint> {
+    let mut lap = points.to_vec();
+    if let (Some(first), Some(last)) = (points.first(), points.last()) {
+        let transfer = process::get_transfer(
+            gas_model,
+            Process::Line,
+            last.volume,
+            last.pressure,
+            first.volume,
+            first.pressure,
+        );
+        lap.push(TracePoint {
+            time: last.time + duration,
+            process: Process::Line,
+            work: transfer.work,
+            heat: transfer.heat,
+            heat_absorbed: transfer.heat_absorbed,
+            ..*first
+        });
+    }
+    lap
+}
+
+// Indices of the points each leg starts from, where a leg is a run of
+// segments travelled the same way.
+pub fn get_leg_starts(points: &[TracePoint]) -> Vec<usize> {
+    (0..points.len().saturating_sub(1))
+        .filter(|&i| i == 0 || points[i].process != points[i + 1].process)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        preset::{self, Preset},
+        thermo::{Gas, Model},
+    };
+
+    // a trace through `states` from the first, each reached along its process
+    fn get_trace(
+        gas_model: &dyn EquationOfState,
+        states: &[(Scalar, Scalar, Process)],
+    ) -> Vec<TracePoint> {
+        let (volume, pressure, _) = states[0];
+        let mut points = vec![TracePoint::start(0., volume, pressure)];
+        for (i, &(new_volume, new_pressure, process)) in states.iter().enumerate().skip(1) {
+            let last = points[i - 1];
+            let transfer = process::get_transfer(
+                gas_model,
+                process,
+                last.volume,
+                last.pressure,
+                new_volume,
+                new_pressure,
+            );
+            points.push(TracePoint {
+                time: i as Scalar,
+                volume: new_volume,
+                pressure: new_pressure,
+                process,
+                work: transfer.work,
+                heat: transfer.heat,
+                heat_absorbed: transfer.heat_absorbed,
+            });
+        }
+        points
+    }
+
+    // a lap of the default Carnot preset between 40 K and 60 K, clockwise
+    fn get_carnot_trace(gas_model: &dyn EquationOfState) -> Vec<TracePoint> {
+        let parameters = Preset::Carnot.default_parameters();
+        assert_eq!(
+            (parameters.cold_tempurature, parameters.hot_tempurature),
+            (40., 60.)
+        );
+        let legs = preset::build(Preset::Carnot, gas_model, &parameters, 60.).unwrap();
+        let mut states = vec![(legs[0].volume, legs[0].pressure, Process::Line)];
+        states.extend(
+            legs.iter()
+                .map(|leg| (leg.new_volume, leg.new_pressure, leg.process)),
+        );
+        get_trace(gas_model, &states)
+    }
+
+    // the same lap travelled the other way, as a refrigerator or heat pump
+    fn get_reversed_carnot_trace(gas_model: &dyn EquationOfState) -> Vec<TracePoint> {
+        let points = get_carnot_trace(gas_model);
+        let mut states = vec![(points[0].volume, points[0].pressure, Process::Line)];
+        states.extend(
+            points
+                .windows(2)
+                .rev()
+                .map(|pair| (pair[0].volume, pair[0].pressure, pair[1].process)),
+        );
+        get_trace(gas_model, &states)
+    }
+
+    // a right triangle travelled counter-clockwise, left open along its
+    // hypotenuse
+    fn get_triangle_trace(gas_model: &dyn EquationOfState) -> Vec<TracePoint> {
+        get_trace(
+            gas_model,
+            &[
+                (20., 10., Process::Line),
+                (40., 10., Process::Isobaric),
+                (40., 20., Process::Isochoric),
+            ],
+        )
+    }
+
+    #[test]
+    fn closed_once_back_after_leaving() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let points = get_carnot_trace(gas_model.as_ref());
+        assert!(is_closed(&points, 1., 1.));
+        assert!(!is_closed(&points[..points.len() - 1], 1., 1.));
+        assert!(!is_closed(&points[..1], 1., 1.));
+        assert!(!is_closed(&[], 1., 1.));
+    }
+
+    #[test]
+    fn summary_counts_the_closing_segment() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let summary = summarize(&get_triangle_trace(gas_model.as_ref()), gas_model.as_ref());
+        // 200 J out along the bottom, 300 J back in along the hypotenuse
+        assert!((summary.net_work + 100.).abs() < 1e-3);
+        assert!(summary.is_reversed());
+        assert!((summary.heat_absorbed - summary.heat_rejected - summary.net_work).abs() < 1e-3);
+    }
+
+    #[test]
+    fn carnot_efficiency() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let summary = summarize(&get_carnot_trace(gas_model.as_ref()), gas_model.as_ref());
+        assert!(!summary.is_reversed());
+        assert!((summary.efficiency - 1. / 3.).abs() < 1e-3);
+        assert!((summary.carnot_efficiency - 1. / 3.).abs() < 1e-3);
+        assert!((summary.absorbed_tempurature - 60.).abs() < 1e-2);
+        assert!((summary.rejected_tempurature - 40.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn closing_adds_the_segment_back_to_the_start() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let points = get_triangle_trace(gas_model.as_ref());
+        let lap = close(&points, gas_model.as_ref(), 2.);
+        assert_eq!(lap.len(), points.len() + 1);
+        assert_eq!(&lap[..points.len()], &points[..]);
+        let (first, last) = (lap[0], lap[lap.len() - 1]);
+        assert_eq!((last.volume, last.pressure), (first.volume, first.pressure));
+        assert_eq!(last.time, points[points.len() - 1].time + 2.);
+        assert_eq!(last.process, Process::Line);
+        assert!((last.work + 300.).abs() < 1e-3);
+        // the closing segment of a closed lap has no length
+        let summary = summarize(&lap, gas_model.as_ref());
+        assert!((summary.net_work + 100.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn legs_start_where_the_process_changes() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        assert_eq!(
+            get_leg_starts(&get_carnot_trace(gas_model.as_ref())),
+            [0, 1, 2, 3]
+        );
+        let points = get_trace(
+            gas_model.as_ref(),
+            &[
+                (20., 10., Process::Line),
+                (30., 10., Process::Isobaric),
+                (40., 10., Process::Isobaric),
+                (40., 20., Process::Isochoric),
+            ],
+        );
+        assert_eq!(get_leg_starts(&points), [0, 2]);
+        assert!(get_leg_starts(&points[..1]).is_empty());
+    }
+
+    #[test]
+    fn reversed_carnot_cops() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let summary = summarize(
+            &get_reversed_carnot_trace(gas_model.as_ref()),
+            gas_model.as_ref(),
+        );
+        assert!(summary.is_reversed());
+        assert!((summary.cooling_cop() - 2.).abs() < 1e-3);
+        assert!((summary.heating_cop() - 3.).abs() < 1e-3);
+        assert!((summary.carnot_cooling_cop() - 2.).abs() < 1e-3);
+        assert!((summary.carnot_heating_cop() - 3.).abs() < 1e-3);
+    }
+}
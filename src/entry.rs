@@ -0,0 +1,178 @@
+use crate::thermo::{EquationOfState, Scalar};
+
+const SCAN_STEPS: u32 = 64;
+const SEARCH_STEPS: u32 = 32;
+
+// "P 12.5 V 40", "t=60, v=20" and the like, in Pa, m^3 and K, as [P, V, T]
+pub fn parse(text: &str) -> Result<[Option<Scalar>; 3], &'static str> {
+    let mut values = [None; 3];
+    let mut symbol = None;
+    for token in text
+        .split(|c: char| c.is_whitespace() || c == '=' || c == ',')
+        .filter(|token| !token.is_empty())
+    {
+        let number = match token.chars().next().map(|c| c.to_ascii_lowercase()) {
+            Some(letter @ ('p' | 'v' | 't')) => {
+                symbol = "pvt".find(letter);
+                &token[1..]
+            }
+            _ => token,
+        };
+        if number.is_empty() {
+            continue;
+        }
+        let (Some(index), Ok(value)) = (symbol.take(), number.parse::<Scalar>()) else {
+            return Err("write it like P 12 V 40");
+        };
+        values[index] = Some(value);
+    }
+    if values.iter().flatten().count() == 2 {
+        Ok(values)
+    } else {
+        Err("give exactly two of P, V and T")
+    }
+}
+
+// (m^3, Pa) of the state fixed by two of [P, V, T], if it lies within `low` to
+// `high`, each as (m^3, Pa)
+pub fn get_state(
+    gas_model: &dyn EquationOfState,
+    values: [Option<Scalar>; 3],
+    low: (Scalar, Scalar),
+    high: (Scalar, Scalar),
+) -> Result<(Scalar, Scalar), &'static str> {
+    let state = match values {
+        [Some(pressure), Some(volume), _] => (volume, pressure),
+        [_, Some(volume), Some(tempurature)] => {
+            (volume, gas_model.get_pressure(volume, tempurature))
+        }
+        [Some(pressure), _, Some(tempurature)] => (
+            find_isobar_volume(gas_model, pressure, tempurature, low.0, high.0)?,
+            pressure,
+        ),
+        _ => return Err("give exactly two of P, V and T"),
+    };
+    if (low.0..=high.0).contains(&state.0) && (low.1..=high.1).contains(&state.1) {
+        let tempurature = values[2].unwrap_or(gas_model.get_tempurature(state.0, state.1));
+        if (gas_model.get_tempurature(state.0, state.1) - tempurature).abs() < 0.5 {
+            return Ok(state);
+        }
+    }
+    Err("that state is off the plot")
+}
+
+// m^3, where the isobar at `pressure` reaches `tempurature` between `low` and
+// `high`. Below the critical point a van der Waals isobar rises, falls and
+// rises again in T, so it is scanned for every crossing before bisecting one.
+fn find_isobar_volume(
+    gas_model: &dyn EquationOfState,
+    pressure: Scalar,
+    tempurature: Scalar,
+    low: Scalar,
+    high: Scalar,
+) -> Result<Scalar, &'static str> {
+    let above = |volume: Scalar| gas_model.get_tempurature(volume, pressure) > tempurature;
+    let volumes = (0..=SCAN_STEPS)
+        .map(|i| low + (high - low) * i as Scalar / SCAN_STEPS as Scalar)
+        .collect::<Vec<_>>();
+    let mut crossings = volumes
+        .windows(2)
+        .filter(|pair| above(pair[0]) != above(pair[1]));
+    let Some(crossing) = crossings.next() else {
+        return Err("that state is off the plot");
+    };
+    if crossings.next().is_some() {
+        return Err("that T is reached at more than one V at that P");
+    }
+    let rising = above(crossing[1]);
+    let (mut low, mut high) = (crossing[0], crossing[1]);
+    for _ in 0..SEARCH_STEPS {
+        let volume = (low + high) / 2.;
+        if above(volume) == rising {
+            high = volume;
+        } else {
+            low = volume;
+        }
+    }
+    Ok((low + high) / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thermo::{Gas, Model, R};
+
+    // the reachable states on the plot in main.rs
+    const HIGH: (Scalar, Scalar) = (79.2, 23.4);
+
+    fn get_low(gas_model: &dyn EquationOfState) -> (Scalar, Scalar) {
+        (gas_model.get_min_volume().max(0.) + 1.6, 1.6)
+    }
+
+    #[test]
+    fn parses_two_values() {
+        assert_eq!(parse("P 12 V 40"), Ok([Some(12.), Some(40.), None]));
+        assert_eq!(parse("t=60, v=20"), Ok([None, Some(20.), Some(60.)]));
+        assert_eq!(parse("p12.5 T 30"), Ok([Some(12.5), None, Some(30.)]));
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        assert_eq!(
+            parse("P 12 V 40 T 50"),
+            Err("give exactly two of P, V and T")
+        );
+        assert_eq!(parse("P 12"), Err("give exactly two of P, V and T"));
+        assert_eq!(parse("12"), Err("write it like P 12 V 40"));
+        assert_eq!(parse("P twelve V 40"), Err("write it like P 12 V 40"));
+    }
+
+    #[test]
+    fn ideal_states() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let low = get_low(gas_model.as_ref());
+        let state = |values| get_state(gas_model.as_ref(), values, low, HIGH);
+        assert_eq!(state([Some(12.), Some(40.), None]), Ok((40., 12.)));
+        let (volume, pressure) = state([None, Some(20.), Some(40.)]).unwrap();
+        assert_eq!(volume, 20.);
+        assert!((pressure - R * 40. / 20.).abs() < 1e-4);
+        let (volume, pressure) = state([Some(10.), None, Some(60.)]).unwrap();
+        assert_eq!(pressure, 10.);
+        assert!((volume - R * 60. / 10.).abs() < 1e-3);
+        assert_eq!(
+            state([Some(12.), Some(100.), None]),
+            Err("that state is off the plot")
+        );
+        assert_eq!(
+            state([Some(10.), None, Some(1e3)]),
+            Err("that state is off the plot")
+        );
+    }
+
+    #[test]
+    fn van_der_waals_isobar_below_the_critical_point() {
+        let gas_model = Model::VanDerWaals.build(Gas::NITROGEN, 1.);
+        let low = get_low(gas_model.as_ref());
+        let state = |tempurature| {
+            get_state(
+                gas_model.as_ref(),
+                [Some(2.), None, Some(tempurature)],
+                low,
+                HIGH,
+            )
+        };
+        // at 2 Pa, T rises from 19.2 K at the left edge to 25.4 K at 12 m^3,
+        // falls to 20.1 K at 40 m^3, then rises to 24.2 K at the right edge
+        assert_eq!(
+            state(22.),
+            Err("that T is reached at more than one V at that P")
+        );
+        assert_eq!(
+            state(25.),
+            Err("that T is reached at more than one V at that P")
+        );
+        let (volume, _) = state(19.7).unwrap();
+        assert!(volume < 12.);
+        assert!((gas_model.get_tempurature(volume, 2.) - 19.7).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod cycle;
+pub mod entry;
+pub mod preset;
+pub mod process;
+pub mod scale;
+pub mod thermo;
+pub mod trace;
@@ -1,32 +1,114 @@
-use bevy::{prelude::*, sprite::Anchor, sprite::MaterialMesh2dBundle};
+use bevy::{
+    prelude::*,
+    render::{
+        camera::ScalingMode,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle},
+};
 use bevy_prototype_lyon::prelude::*;
 use bevy_xpbd_2d::{math::*, prelude::*};
 use rand::prelude::*;
+use std::collections::VecDeque;
+use therm::{
+    cycle::{self, CycleSummary},
+    entry,
+    preset::{self, Leg, Parameter, Parameters, Preset},
+    process::{self, Process},
+    scale::{Dimensions, ParticleScale},
+    thermo::{EquationOfState, Gas, Model, Quantity},
+    trace::TracePoint,
+};
 
 const BOX_WIDTH: Scalar = 1000.;
 const BOX_HEIGHT: Scalar = 250.;
-const BOX_POSITION: Vec2 = Vec2 { x: 0., y: 110. };
+// the left edge of the gas sits above the left edge of the plot
+const BOX_POSITION: Vec2 = Vec2 { x: 60., y: 110. };
 const BOX_THICKNESS: Scalar = 32.;
 
 const PLOT_WIDTH: Scalar = BOX_WIDTH - BOX_THICKNESS * 6.;
 const PLOT_HEIGHT: Scalar = BOX_HEIGHT;
 const PLOT_POSITION: Vec2 = Vec2 { x: 0., y: -190. };
 
+// the smaller state-space plots stacked right of the box
+const SIDE_PLOT_WIDTH: Scalar = 400.;
+const SIDE_PLOT_HEIGHT: Scalar = 170.;
+const SIDE_PLOT_X: Scalar = 950.;
+const SIDE_PLOT_SAMPLES: i64 = 200;
+
+// the histogram of particle speeds, between the box and the side plots
+const SPEED_PLOT_WIDTH: Scalar = 240.;
+const SPEED_PLOT_HEIGHT: Scalar = 150.;
+const SPEED_PLOT_POSITION: Vec2 = Vec2 { x: 570., y: 380. };
+const SPEED_BINS: usize = 24;
+const SPEED_RANGE: Scalar = 4.; // widest speed shown, in thermal speeds
+const SPEED_SAMPLES: i64 = 100;
+
+const PHYSICS_MAX_DT: Scalar = 1. / 30.; // s, the most one physics step covers
+
+const BERENDSEN_TIME: Scalar = 0.5; // s, for the particles to relax to T
+const ANDERSEN_RATE: Scalar = 2.; // s^-1, thermalizing collisions per particle
+
 const GRID_WIDTH_OUT: i64 = 8;
 const GRID_HEIGHT_OUT: i64 = 4;
+const PARTICLES_PER_MOLE: Scalar = ((GRID_WIDTH_OUT * 2 + 1) * (GRID_HEIGHT_OUT * 2 + 1)) as Scalar;
 
-const PARTICLE_MASS: Scalar = 1e-3; // kg
+const MOLES_STEP: Scalar = 0.25;
+const MAX_MOLES: Scalar = 3.;
+
+const PARTICLE_MASS: Scalar = 10.; // kg
 const PARTICLE_RADIUS: Scalar = 4.;
+// the region the particles' centres can reach starts at PARTICLE_LEFT and is
+// PARTICLE_HEIGHT tall
+const PARTICLE_LEFT: Scalar = BOX_POSITION.x - BOX_WIDTH / 2. + BOX_THICKNESS + PARTICLE_RADIUS;
+const PARTICLE_HEIGHT: Scalar = BOX_HEIGHT - 2. * BOX_THICKNESS - 2. * PARTICLE_RADIUS;
+// m per px
+const LENGTH_SCALE: Scalar = 0.01;
+// m, chosen so that the region is as wide as the volume is on the plot
+const BOX_DEPTH: Scalar = 1. / (VOLUME_SCALE * PARTICLE_HEIGHT * LENGTH_SCALE * LENGTH_SCALE);
 const HANDLE_RADIUS: Scalar = 16.;
 
+// the camera zooms out until at least this much of the world is visible
+const VIEW_WIDTH: Scalar = 2400.;
+const VIEW_HEIGHT: Scalar = 1000.;
+
+// top left corner of the column of readings left of the box
+const SIDEBAR_POSITION: Vec2 = Vec2 { x: -1180., y: 480. };
+
 const TEXT_OFFSET: Scalar = 10.;
 const FONT_SIZE: Scalar = 40.;
+const READING_FONT_SIZE: Scalar = 28.;
+
+const RESERVOIR_WIDTH: Scalar = 60.;
+const RESERVOIR_GAP: Scalar = 40.; // between a detached reservoir and the wall
+const RESERVOIR_CONDUCTANCE: Scalar = 5.; // W K^-1
+const RESERVOIR_STEP: Scalar = 10.; // K
 
-const R: Scalar = 8.314; // J mol^-1 K^-1
-const CP: Scalar = 5. / 2. * R; // J mol^-1 K^-1
-const CV: Scalar = 3. / 2. * R; // J mol^-1 K^-1
-const GAMMA: Scalar = CP / CV;
-const N: Scalar = 1.; // actually n in mols
+const PRESET_SPEED: Scalar = 0.5; // legs per second
+const PRESET_SAMPLES: i64 = 60; // trace points per leg
+const MIN_PLAYBACK_SPEED: Scalar = 0.125;
+const MAX_PLAYBACK_SPEED: Scalar = 8.;
+
+const POLYTROPIC_STEP: Scalar = 0.1;
+// the curves drawn through the current state on every plot; the polytrope
+// takes the current exponent when drawn
+const PROCESS_CURVES: [Process; 5] = [
+    Process::Isobaric,
+    Process::Isochoric,
+    Process::Isothermal,
+    Process::Adiabatic,
+    Process::Polytropic(1.),
+];
+
+const STATE_ENTRY_STEPS: i64 = 60; // trace points on the way to a typed state
+
+const BACKGROUND_CURVES: i64 = 8; // of each family
+const HEATMAP_RESOLUTION: Scalar = 4.; // plot pixels per heatmap texel
+
+// the wall pressure the particles measure is averaged over this long, split
+// into bins to estimate its spread
+const GAUGE_WINDOW: Scalar = 4.; // s
+const GAUGE_BINS: usize = 8;
 
 const PRESSURE_SCALE: Scalar = 10.;
 const VOLUME_SCALE: Scalar = 10.;
@@ -40,31 +122,253 @@ struct Piston;
 #[derive(Component)]
 struct Particle;
 
+// a particle's velocity going into the last physics step
+#[derive(Component)]
+struct LastVelocity(Vec2);
+
 #[derive(Component)]
 struct BoxFloorOrCeiling;
 
+// any side of the box, the piston included
+#[derive(Component)]
+struct Wall;
+
+// the left wall, which the reservoirs touch
+#[derive(Component)]
+struct HeatedWall;
+
+#[derive(Component)]
+struct ProcessLine(Process); // through the current state on the PV plot
+
+#[derive(Component)]
+struct BackgroundCurve(Process); // draws the whole family of this process
+
+#[derive(Component)]
+struct Heatmap;
+
+#[derive(Component)]
+struct TraceLine;
+
+#[derive(Component)]
+struct CycleArea;
+
+// a side plot
+#[derive(Clone, Copy)]
+struct Plot {
+    x: Quantity,
+    y: Quantity,
+    position: Vec2,
+}
+
+#[derive(Component)]
+struct PlotMarker;
+
 #[derive(Component)]
-struct IsobaricLine;
+struct PlotTrace;
 
 #[derive(Component)]
-struct IsochoricLine;
+struct PlotLegArea;
 
 #[derive(Component)]
-struct IsothermicLine;
+struct PlotCurve(Process);
 
 #[derive(Component)]
-struct AdiabaticLine;
+struct SpeedHistogram;
+
+#[derive(Component)]
+struct SpeedCurve; // the Maxwell-Boltzmann distribution the histogram should match
+
+// a body at fixed tempurature that exchanges heat with the gas while it
+// touches the left wall
+#[derive(Component)]
+struct Reservoir {
+    name: &'static str,
+    tempurature: Scalar, // K
+    attached: bool,
+}
+
+#[derive(Component)]
+struct PresetLine(usize); // which leg of the preset cycle it draws
 
 #[derive(Component)]
 struct TempuratureReading;
 
+#[derive(Component)]
+struct ReservoirReading;
+
+#[derive(Component)]
+struct PresetReading;
+
+#[derive(Component)]
+struct PlaybackReading;
+
+#[derive(Component)]
+struct BackgroundReading;
+
+#[derive(Component)]
+struct HoverTooltip;
+
+#[derive(Component)]
+struct StateEntryReading;
+
+#[derive(Component)]
+struct CycleReading;
+
+#[derive(Component)]
+struct ProcessReading;
+
+#[derive(Component)]
+struct GasReading;
+
 #[derive(Resource)]
 struct Data {
     handle_x: Scalar,
     handle_y: Scalar,
-    work: Scalar,
+    work: Scalar,           // J, done by the gas since the reference state
+    reference: Vec2,        // handle position the bookkeeping started from
+    leg_work: Scalar,       // J, done by the gas since leg_start
+    leg_start: Vec2,        // handle position the current drag started from
+    reservoir_heat: Scalar, // J, absorbed from reservoirs since the reference state
+    work_convention: WorkConvention,
+    gas: Gas,
+    model: Model,
+    moles: Scalar,
+    process: Process, // what dragging the handle is locked to
+    polytropic_exponent: Scalar,
+    dimensions: Dimensions, // that the particles' speeds stand for
+    thermostat: Thermostat,
+}
+
+impl Data {
+    fn start_leg(&mut self) {
+        self.leg_start = Vec2::new(self.handle_x, self.handle_y);
+        self.leg_work = 0.;
+    }
+
+    fn reset(&mut self) {
+        self.reference = Vec2::new(self.handle_x, self.handle_y);
+        self.work = 0.;
+        self.reservoir_heat = 0.;
+        self.start_leg();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum WorkConvention {
+    ByGas, // Q = dU + W, the engineering convention
+    OnGas, // Q = dU - W, the IUPAC convention
 }
 
+// How heat reaches the particles, either from the state on the plot or from
+// the reservoirs through the left wall.
+#[derive(Clone, Copy, PartialEq)]
+enum Thermostat {
+    Rescale,   // every speed scaled by the same factor every frame
+    Berendsen, // the same, but relaxing to T over BERENDSEN_TIME
+    Andersen,  // particles now and then redrawn from the Maxwell distribution
+    HotWall,   // particles leave an attached reservoir with Maxwellian velocities
+    Off,       // only the moving piston changes the particles' energy, which
+               // in the plane heats them like a gas with f = 2
+}
+
+impl Thermostat {
+    fn name(&self) -> &'static str {
+        match self {
+            Thermostat::Rescale => "rescale",
+            Thermostat::Berendsen => "Berendsen",
+            Thermostat::Andersen => "Andersen",
+            Thermostat::HotWall => "hot wall",
+            Thermostat::Off => "no",
+        }
+    }
+}
+
+#[derive(Resource)]
+struct GasModel(Box<dyn EquationOfState>);
+
+// every state the handle has been dragged through, oldest first
+#[derive(Resource, Default)]
+struct History {
+    points: Vec<TracePoint>,
+    leg_start: usize, // index of the state the current leg started from
+}
+
+#[derive(Resource, Default)]
+struct Cycle {
+    start: usize, // index into History where the current lap began
+    points: Vec<TracePoint>,
+    summary: Option<CycleSummary>,
+}
+
+// the textbook cycle picked from the preset menu, if any
+#[derive(Resource)]
+struct PresetCycle {
+    preset: Option<Preset>,
+    parameters: Parameters,
+    parameter: Parameter, // the one the menu is editing
+    legs: Result<Vec<Leg>, &'static str>,
+}
+
+// a closed lap the handle can walk around by itself, either a preset or the
+// last cycle traced by hand
+#[derive(Resource)]
+struct Playback {
+    lap: Vec<TracePoint>, // ends back on its first state
+    leg_starts: Vec<usize>,
+    index: usize,  // the point of the lap the handle is on
+    clock: Scalar, // s into the lap, in the lap's own time
+    speed: Scalar,
+    playing: bool,
+}
+
+impl Playback {
+    fn load(&mut self, lap: Vec<TracePoint>) {
+        self.leg_starts = cycle::get_leg_starts(&lap);
+        self.lap = lap;
+        self.index = 0;
+        self.clock = 0.;
+    }
+
+    // s, the lap's own length in time
+    fn get_duration(&self) -> Scalar {
+        match (self.lap.first(), self.lap.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.,
+        }
+    }
+}
+
+// the panel for typing in an exact state to move the handle to
+#[derive(Resource, Default)]
+struct StateEntry {
+    text: String,
+    typing: bool, // while set, keys go to the panel instead of their usual jobs
+    message: String,
+}
+
+// momentum the particles have handed to the walls, per frame, over the last
+// GAUGE_WINDOW
+#[derive(Resource, Default)]
+struct PressureGauge {
+    impulses: VecDeque<(Scalar, Scalar)>, // (s, kg px s^-1)
+}
+
+// what is drawn across the whole PV plot behind the curves through the handle
+#[derive(Resource, Clone, Copy, PartialEq)]
+enum Background {
+    Plain,
+    Contours,          // evenly spaced isotherms and adiabats
+    Heatmap(Quantity), // color by a state function
+}
+
+#[derive(Resource)]
+struct ParticleAssets {
+    mesh: Mesh2dHandle,
+    material: bevy::asset::Handle<ColorMaterial>,
+}
+
+// the plot maps handle pixels to physical state, everything else lives in thermo
+
 // m^3
 fn get_volume(handle_x: Scalar) -> Scalar {
     (handle_x - (PLOT_POSITION.x - PLOT_WIDTH / 2.)) / VOLUME_SCALE
@@ -76,13 +380,38 @@ fn get_pressure(handle_y: Scalar) -> Scalar {
 }
 
 // K
-fn get_tempurature(handle_x: Scalar, handle_y: Scalar) -> Scalar {
-    get_volume(handle_x) * get_pressure(handle_y) / (N * R)
+fn get_tempurature(gas_model: &GasModel, handle_x: Scalar, handle_y: Scalar) -> Scalar {
+    gas_model
+        .0
+        .get_tempurature(get_volume(handle_x), get_pressure(handle_y))
 }
 
 // J
-fn get_energy(handle_x: Scalar, handle_y: Scalar) -> Scalar {
-    3. / 2. * N * R * get_tempurature(handle_x, handle_y)
+fn get_energy(gas_model: &GasModel, handle_x: Scalar, handle_y: Scalar) -> Scalar {
+    gas_model
+        .0
+        .get_energy(get_volume(handle_x), get_pressure(handle_y))
+}
+
+fn get_particle_scale(data: &Data) -> ParticleScale {
+    ParticleScale {
+        dimensions: data.dimensions,
+        length: LENGTH_SCALE,
+        depth: BOX_DEPTH,
+        mass: PARTICLE_MASS,
+        particles: (data.moles * PARTICLES_PER_MOLE).round(),
+        moles: data.moles,
+    }
+}
+
+// px, the right edge of the region the particles' centres can reach
+fn get_particle_right(data: &Data) -> Scalar {
+    PARTICLE_LEFT + get_particle_scale(data).get_width(get_volume(data.handle_x), PARTICLE_HEIGHT)
+}
+
+// px, centre of the piston resting on the particles' region
+fn get_piston_x(data: &Data) -> Scalar {
+    get_particle_right(data) + PARTICLE_RADIUS + BOX_THICKNESS / 2.
 }
 
 fn get_handle_x(volume: Scalar) -> Scalar {
@@ -93,6 +422,52 @@ fn get_handle_y(pressure: Scalar) -> Scalar {
     pressure * PRESSURE_SCALE + PLOT_POSITION.y - PLOT_HEIGHT / 2.
 }
 
+// keeps the handle right of the volume the molecules themselves take up
+fn get_min_handle_x(gas_model: &GasModel) -> Scalar {
+    (PLOT_POSITION.x - PLOT_WIDTH / 2.).max(get_handle_x(gas_model.0.get_min_volume()))
+        + HANDLE_RADIUS
+}
+
+// samples handle_y over every pixel column of the plot, clipped to the plot
+fn build_plot_curve(get_y: impl Fn(Scalar) -> Scalar) -> Path {
+    let mut path_builder = PathBuilder::new();
+    add_plot_curve(&mut path_builder, get_y);
+    path_builder.build()
+}
+
+fn add_plot_curve(path_builder: &mut PathBuilder, get_y: impl Fn(Scalar) -> Scalar) {
+    let bottom = PLOT_POSITION.y - PLOT_HEIGHT / 2.;
+    let top = PLOT_POSITION.y + PLOT_HEIGHT / 2.;
+    let mut last: Option<Vec2> = None;
+    let mut drawing = false;
+    for handle_x in
+        (PLOT_POSITION.x - PLOT_WIDTH / 2.) as i64..=(PLOT_POSITION.x + PLOT_WIDTH / 2.) as i64
+    {
+        let point = Vec2 {
+            x: handle_x as Scalar,
+            y: get_y(handle_x as Scalar),
+        };
+        let visible = last.is_some_and(|last| {
+            point.y.is_finite()
+                && ((bottom..=top).contains(&last.y) || (bottom..=top).contains(&point.y))
+        });
+        if let Some(last) = last.filter(|_| visible) {
+            if !drawing {
+                path_builder.move_to(Vec2 {
+                    x: last.x,
+                    y: last.y.clamp(bottom, top),
+                });
+            }
+            path_builder.line_to(Vec2 {
+                x: point.x,
+                y: point.y.clamp(bottom, top),
+            });
+        }
+        drawing = visible;
+        last = point.y.is_finite().then_some(point);
+    }
+}
+
 fn main() {
     App::new()
         .add_plugins((
@@ -108,263 +483,2246 @@ fn main() {
         ))
         .insert_resource(ClearColor(Color::rgb(0.05, 0.05, 0.1)))
         .insert_resource(Gravity::ZERO)
+        // one physics step per frame, so the piston's velocity can be set to
+        // land it where the handle is
+        .insert_resource(PhysicsTimestep::Variable {
+            max_dt: PHYSICS_MAX_DT,
+        })
         .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                handle_gas_input,
+                handle_process_input,
+                handle_bookkeeping_input,
+                handle_trace_input,
+                handle_background_input,
+                handle_reservoir_input,
+                handle_preset_input,
+                handle_playback_input,
+            )
+                .run_if(not(is_typing)),
+        )
         .add_systems(
             Update,
             (
                 handle_pv_input,
+                handle_state_entry,
+                play_cycle,
+                exchange_heat,
+                detect_cycle,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
                 move_handle,
                 move_piston,
                 move_box_floor_and_ceiling,
-                move_isobaric,
-                move_isochoric,
-                move_isothermic,
-                move_adiabatic,
+                move_process_lines,
+                move_background_curves,
+                move_heatmap,
+                move_trace,
+                move_cycle_area,
+                move_reservoirs,
+                move_preset_lines,
+                (
+                    update_plot_axes,
+                    (
+                        move_plot_markers,
+                        move_plot_curves,
+                        move_plot_traces,
+                        move_plot_leg_areas,
+                    ),
+                )
+                    .chain(),
+                move_speed_histogram,
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                fix_particles_count,
                 fix_particles_location,
                 fix_particles_energy,
+                measure_wall_impulses
+                    .after(fix_particles_location)
+                    .after(fix_particles_energy),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
                 update_tempurature_reading,
+                update_gas_reading,
+                update_cycle_reading,
+                update_process_reading,
+                update_reservoir_reading,
+                update_preset_reading,
+                update_playback_reading,
+                update_background_reading,
+                update_hover_tooltip,
+                update_state_entry_reading,
             ),
         )
         .run();
 }
 
+// (m^3, Pa) corners of the region of the PV plot the handle can reach
+fn get_state_bounds(gas_model: &GasModel) -> (Vec2, Vec2) {
+    (
+        Vec2 {
+            x: get_volume(get_min_handle_x(gas_model)),
+            y: get_pressure(PLOT_POSITION.y - PLOT_HEIGHT / 2. + HANDLE_RADIUS),
+        },
+        Vec2 {
+            x: get_volume(PLOT_POSITION.x + PLOT_WIDTH / 2. - HANDLE_RADIUS),
+            y: get_pressure(PLOT_POSITION.y + PLOT_HEIGHT / 2. - HANDLE_RADIUS),
+        },
+    )
+}
+
+// side plot ranges, which cover every state the handle can reach; copied onto
+// every entity drawn on the plot, and only worked out again when the gas
+// model changes
+#[derive(Component, Clone, Copy)]
+struct PlotAxes {
+    plot: Plot,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl PlotAxes {
+    fn new(plot: &Plot, gas_model: &GasModel) -> PlotAxes {
+        let (min_x, max_x) = get_quantity_range(gas_model, plot.x);
+        let (min_y, max_y) = get_quantity_range(gas_model, plot.y);
+        let mut min = Vec2::new(min_x, min_y);
+        let max = Vec2::new(max_x, max_y);
+        if plot.x.is_absolute() {
+            min.x = 0.;
+        }
+        if plot.y.is_absolute() {
+            min.y = 0.;
+        }
+        PlotAxes {
+            plot: *plot,
+            min,
+            max,
+        }
+    }
+
+    fn get_bottom(&self) -> Scalar {
+        self.plot.position.y - SIDE_PLOT_HEIGHT / 2.
+    }
+
+    fn get_position(&self, gas_model: &GasModel, volume: Scalar, pressure: Scalar) -> Vec2 {
+        let size = Vec2::new(SIDE_PLOT_WIDTH, SIDE_PLOT_HEIGHT);
+        let value = get_plot_values(&self.plot, gas_model, volume, pressure);
+        self.plot.position - size / 2.
+            + ((value - self.min) / (self.max - self.min)).clamp(Vec2::ZERO, Vec2::ONE) * size
+    }
+}
+
+// smallest and largest value of `quantity` over the states the handle can reach
+fn get_quantity_range(gas_model: &GasModel, quantity: Quantity) -> (Scalar, Scalar) {
+    const SAMPLES: i64 = 16;
+    let (low, high) = get_state_bounds(gas_model);
+    let mut min = Scalar::INFINITY;
+    let mut max = Scalar::NEG_INFINITY;
+    for i in 0..=SAMPLES {
+        for j in 0..=SAMPLES {
+            let value = quantity.get(
+                gas_model.0.as_ref(),
+                low.x + (high.x - low.x) * i as Scalar / SAMPLES as Scalar,
+                low.y + (high.y - low.y) * j as Scalar / SAMPLES as Scalar,
+            );
+            min = min.min(value);
+            max = max.max(value);
+        }
+    }
+    (min, max)
+}
+
+fn get_plot_values(plot: &Plot, gas_model: &GasModel, volume: Scalar, pressure: Scalar) -> Vec2 {
+    Vec2 {
+        x: plot.x.get(gas_model.0.as_ref(), volume, pressure),
+        y: plot.y.get(gas_model.0.as_ref(), volume, pressure),
+    }
+}
+
+// `process` as drawn through the current state, with the current exponent
+fn get_curve_process(data: &Data, process: Process) -> Process {
+    match process {
+        Process::Polytropic(_) => Process::Polytropic(data.polytropic_exponent),
+        process => process,
+    }
+}
+
+fn get_process_color(process: Process) -> Color {
+    match process {
+        Process::Isobaric => Color::rgb_u8(5, 46, 22),
+        Process::Isochoric => Color::rgb_u8(23, 37, 84),
+        Process::Isothermal => Color::rgb_u8(69, 10, 10),
+        Process::Adiabatic => Color::rgb_u8(59, 7, 100),
+        Process::Polytropic(_) => Color::rgb_u8(8, 51, 68),
+        Process::Line => Color::rgb_u8(245, 158, 11),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn handle_pv_input(
     buttons: Res<Input<MouseButton>>,
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform), With<Camera>>,
+    time: Res<Time>,
     mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    mut playback: ResMut<Playback>,
+    gas_model: Res<GasModel>,
 ) {
-    if let Some(mouse_position) = windows.single().cursor_position().and_then(|cursor| {
-        camera_q
-            .single()
-            .0
-            .viewport_to_world_2d(camera_q.single().1, cursor)
-    }) {
-        if buttons.pressed(MouseButton::Left)
-            && mouse_position.x > PLOT_POSITION.x - PLOT_WIDTH / 2.
-            && mouse_position.x < PLOT_POSITION.x + PLOT_WIDTH / 2.
-            && mouse_position.y > PLOT_POSITION.y - PLOT_HEIGHT / 2.
-            && mouse_position.y < PLOT_POSITION.y + PLOT_HEIGHT / 2.
-        {
-            let new_handle_x = mouse_position.x.clamp(
-                PLOT_POSITION.x - PLOT_WIDTH / 2. + HANDLE_RADIUS,
-                PLOT_POSITION.x + PLOT_WIDTH / 2. - HANDLE_RADIUS,
-            );
-            let new_handle_y = mouse_position.y.clamp(
-                PLOT_POSITION.y - PLOT_HEIGHT / 2. + HANDLE_RADIUS,
-                PLOT_POSITION.y + PLOT_HEIGHT / 2. - HANDLE_RADIUS,
+    if let Some(mouse_position) = get_plot_cursor(&windows, &camera_q) {
+        if buttons.pressed(MouseButton::Left) {
+            if buttons.just_pressed(MouseButton::Left) {
+                // tracing by hand replaces whatever was being played
+                playback.playing = false;
+                playback.lap.clear();
+                data.start_leg();
+                history.leg_start = history.points.len().saturating_sub(1);
+            }
+            let Vec2 {
+                x: new_handle_x,
+                y: new_handle_y,
+            } = project_onto_process(&data, &gas_model, mouse_position);
+            if new_handle_x == data.handle_x && new_handle_y == data.handle_y {
+                return;
+            }
+            let process = data.process;
+            move_state(
+                &mut data,
+                &mut history,
+                &gas_model,
+                process,
+                Vec2::new(new_handle_x, new_handle_y),
+                time.elapsed_seconds(),
             );
-            data.work -= (get_pressure(data.handle_y) + get_pressure(new_handle_y))
-                * (get_volume(data.handle_x) - get_volume(new_handle_x))
-                / 2.;
-            data.handle_x = new_handle_x;
-            data.handle_y = new_handle_y;
         }
     }
 }
 
-fn move_handle(mut handles: Query<&mut Transform, With<Handle>>, data: Res<Data>) {
-    for mut transform in &mut handles {
-        transform.translation.x = data.handle_x;
-        transform.translation.y = data.handle_y;
-    }
+// world position of the cursor, if it is over the PV plot
+fn get_plot_cursor(
+    windows: &Query<&Window>,
+    camera_q: &Query<(&Camera, &GlobalTransform), With<Camera>>,
+) -> Option<Vec2> {
+    let (camera, camera_transform) = camera_q.single();
+    windows
+        .single()
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+        .filter(|mouse_position| {
+            mouse_position.x > PLOT_POSITION.x - PLOT_WIDTH / 2.
+                && mouse_position.x < PLOT_POSITION.x + PLOT_WIDTH / 2.
+                && mouse_position.y > PLOT_POSITION.y - PLOT_HEIGHT / 2.
+                && mouse_position.y < PLOT_POSITION.y + PLOT_HEIGHT / 2.
+        })
 }
 
-fn move_piston(mut pistons: Query<&mut Position, With<Piston>>, data: Res<Data>) {
-    for mut position in &mut pistons {
-        position.x = data.handle_x;
+// moves the handle to `new_handle` along `process`, keeping the trace and the
+// work bookkeeping in step
+fn move_state(
+    data: &mut Data,
+    history: &mut History,
+    gas_model: &GasModel,
+    process: Process,
+    new_handle: Vec2,
+    time: Scalar,
+) {
+    if history.points.is_empty() {
+        history.points.push(TracePoint::start(
+            time,
+            get_volume(data.handle_x),
+            get_pressure(data.handle_y),
+        ));
+    }
+    let transfer = process::get_transfer(
+        gas_model.0.as_ref(),
+        process,
+        get_volume(data.handle_x),
+        get_pressure(data.handle_y),
+        get_volume(new_handle.x),
+        get_pressure(new_handle.y),
+    );
+    // a point less than a pixel past the one before it is moved on rather than
+    // followed by another, so a slow drag doesn't add a point every frame
+    let count = history.points.len();
+    if count >= 2 && count - 2 >= history.leg_start {
+        let (before, last) = (history.points[count - 2], history.points[count - 1]);
+        if last.process == process
+            && Vec2::new(get_handle_x(before.volume), get_handle_y(before.pressure)).distance(
+                Vec2::new(get_handle_x(last.volume), get_handle_y(last.pressure)),
+            ) < 1.
+        {
+            history.points.pop();
+        }
     }
+    let start = history.points[history.points.len() - 1];
+    let trace_transfer = process::get_transfer(
+        gas_model.0.as_ref(),
+        process,
+        start.volume,
+        start.pressure,
+        get_volume(new_handle.x),
+        get_pressure(new_handle.y),
+    );
+    history.points.push(TracePoint {
+        time,
+        volume: get_volume(new_handle.x),
+        pressure: get_pressure(new_handle.y),
+        process,
+        work: trace_transfer.work,
+        heat: trace_transfer.heat,
+        heat_absorbed: trace_transfer.heat_absorbed,
+    });
+    data.work += transfer.work;
+    data.leg_work += transfer.work;
+    data.handle_x = new_handle.x;
+    data.handle_y = new_handle.y;
 }
 
-fn fix_particles_location(mut particles: Query<&mut Position, With<Particle>>, data: Res<Data>) {
-    let mut rng = rand::thread_rng();
-    for mut position in &mut particles {
-        if position.x < BOX_POSITION.x - BOX_WIDTH / 2.
-            || position.x > data.handle_x - BOX_THICKNESS / 2.
-            || position.y < BOX_POSITION.y - BOX_HEIGHT / 2.
-            || position.y > BOX_POSITION.y + BOX_HEIGHT / 2.
-        {
-            position.x = rng.gen_range(
-                BOX_POSITION.x - BOX_WIDTH / 2. + BOX_THICKNESS + PARTICLE_RADIUS
-                    ..data.handle_x - BOX_THICKNESS / 2. - PARTICLE_RADIUS,
-            );
-            position.y = rng.gen_range(
-                BOX_POSITION.y - BOX_HEIGHT / 2. + BOX_THICKNESS + PARTICLE_RADIUS
-                    ..BOX_POSITION.y + BOX_HEIGHT / 2. - BOX_THICKNESS - PARTICLE_RADIUS,
-            );
+// the point the handle should move to for a cursor at `mouse_position`
+fn project_onto_process(data: &Data, gas_model: &GasModel, mouse_position: Vec2) -> Vec2 {
+    let min_x = get_min_handle_x(gas_model);
+    let max_x = PLOT_POSITION.x + PLOT_WIDTH / 2. - HANDLE_RADIUS;
+    let min_y = PLOT_POSITION.y - PLOT_HEIGHT / 2. + HANDLE_RADIUS;
+    let max_y = PLOT_POSITION.y + PLOT_HEIGHT / 2. - HANDLE_RADIUS;
+    match data.process {
+        Process::Line => Vec2 {
+            x: mouse_position.x.clamp(min_x, max_x),
+            y: mouse_position.y.clamp(min_y, max_y),
+        },
+        Process::Isochoric => Vec2 {
+            x: data.handle_x,
+            y: mouse_position.y.clamp(min_y, max_y),
+        },
+        process => {
+            // closest point on the curve through the handle, in quarter pixels
+            let mut closest = Vec2 {
+                x: data.handle_x,
+                y: data.handle_y,
+            };
+            for step in 0..=((max_x - min_x) * 4.) as i64 {
+                let handle_x = min_x + step as Scalar / 4.;
+                let Some(pressure) = process.get_pressure(
+                    gas_model.0.as_ref(),
+                    get_volume(data.handle_x),
+                    get_pressure(data.handle_y),
+                    get_volume(handle_x),
+                ) else {
+                    continue;
+                };
+                let point = Vec2 {
+                    x: handle_x,
+                    y: get_handle_y(pressure),
+                };
+                if (min_y..=max_y).contains(&point.y)
+                    && point.distance(mouse_position) < closest.distance(mouse_position)
+                {
+                    closest = point;
+                }
+            }
+            closest
         }
     }
 }
 
-fn fix_particles_energy(
-    mut particles: Query<&mut LinearVelocity, With<Particle>>,
-    data: Res<Data>,
+fn handle_process_input(
+    keys: Res<Input<KeyCode>>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
 ) {
-    let mut current_energy = 0.;
-    for velocity in &particles {
-        current_energy += PARTICLE_MASS * velocity.length_squared() / 2.;
+    for (key, process) in [
+        (KeyCode::F, Process::Line),
+        (KeyCode::T, Process::Isothermal),
+        (KeyCode::P, Process::Isobaric),
+        (KeyCode::V, Process::Isochoric),
+        (KeyCode::S, Process::Adiabatic),
+        (KeyCode::Y, Process::Polytropic(data.polytropic_exponent)),
+    ] {
+        if keys.just_pressed(key) && data.process != process {
+            data.process = process;
+            data.start_leg();
+            history.leg_start = history.points.len().saturating_sub(1);
+        }
+    }
+    let exponent = data.polytropic_exponent;
+    if keys.just_pressed(KeyCode::J) {
+        data.polytropic_exponent -= POLYTROPIC_STEP;
     }
-    let scale = (get_energy(data.handle_x, data.handle_y) / current_energy).sqrt();
-    for mut velocity in &mut particles {
-        velocity.x *= scale;
-        velocity.y *= scale;
+    if keys.just_pressed(KeyCode::K) {
+        data.polytropic_exponent += POLYTROPIC_STEP;
+    }
+    if data.polytropic_exponent != exponent {
+        // keep n on whole steps rather than let the sums drift
+        data.polytropic_exponent =
+            (data.polytropic_exponent / POLYTROPIC_STEP).round() * POLYTROPIC_STEP;
+        if let Process::Polytropic(_) = data.process {
+            data.process = Process::Polytropic(data.polytropic_exponent);
+            data.start_leg();
+            history.leg_start = history.points.len().saturating_sub(1);
+        }
     }
 }
 
-fn move_box_floor_and_ceiling(
-    mut walls: Query<&mut Transform, With<BoxFloorOrCeiling>>,
-    data: Res<Data>,
-) {
-    for mut transform in &mut walls {
-        transform.scale.x =
-            (data.handle_x + BOX_THICKNESS / 2. - (BOX_POSITION.x - BOX_WIDTH / 2.)) / BOX_WIDTH;
-        transform.translation.x =
-            (data.handle_x + BOX_THICKNESS / 2. + BOX_POSITION.x - BOX_WIDTH / 2.) / 2.;
+fn handle_bookkeeping_input(keys: Res<Input<KeyCode>>, mut data: ResMut<Data>) {
+    if keys.just_pressed(KeyCode::R) {
+        data.reset();
+    }
+    if keys.just_pressed(KeyCode::W) {
+        data.work_convention = match data.work_convention {
+            WorkConvention::ByGas => WorkConvention::OnGas,
+            WorkConvention::OnGas => WorkConvention::ByGas,
+        };
     }
 }
 
-fn move_isobaric(mut isobarics: Query<&mut Path, With<IsobaricLine>>, data: Res<Data>) {
-    for mut path in &mut isobarics {
-        let mut path_builder = PathBuilder::new();
-        path_builder.move_to(Vec2 {
-            x: PLOT_POSITION.x - PLOT_WIDTH / 2.,
-            y: data.handle_y,
-        });
-        path_builder.line_to(Vec2 {
-            x: PLOT_POSITION.x + PLOT_WIDTH / 2.,
-            y: data.handle_y,
-        });
-        *path = path_builder.build();
+fn handle_gas_input(
+    keys: Res<Input<KeyCode>>,
+    mut data: ResMut<Data>,
+    mut gas_model: ResMut<GasModel>,
+    mut history: ResMut<History>,
+    mut cycle: ResMut<Cycle>,
+) {
+    let (gas, model, moles) = (data.gas, data.model, data.moles);
+    let tempurature = get_tempurature(&gas_model, data.handle_x, data.handle_y);
+    if keys.just_pressed(KeyCode::G) {
+        data.gas = match data.gas.name {
+            "helium" => Gas::NITROGEN,
+            "nitrogen" => Gas::METHANE,
+            _ => Gas::HELIUM,
+        };
+    }
+    if keys.just_pressed(KeyCode::M) {
+        data.model = match data.model {
+            Model::Ideal => Model::VanDerWaals,
+            Model::VanDerWaals => Model::Ideal,
+        };
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) && data.gas.degrees_of_freedom > 1. {
+        data.gas.degrees_of_freedom -= 1.;
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        data.gas.degrees_of_freedom += 1.;
+    }
+    if keys.just_pressed(KeyCode::D) {
+        data.dimensions = match data.dimensions {
+            Dimensions::Two => Dimensions::Three,
+            Dimensions::Three => Dimensions::Two,
+        };
+    }
+    if keys.just_pressed(KeyCode::Q) {
+        data.thermostat = match data.thermostat {
+            Thermostat::Rescale => Thermostat::Berendsen,
+            Thermostat::Berendsen => Thermostat::Andersen,
+            Thermostat::Andersen => Thermostat::HotWall,
+            Thermostat::HotWall => Thermostat::Off,
+            Thermostat::Off => Thermostat::Rescale,
+        };
+    }
+    if keys.just_pressed(KeyCode::Equals) && data.moles < MAX_MOLES {
+        data.moles += MOLES_STEP;
+    }
+    if keys.just_pressed(KeyCode::Minus) && data.moles > MOLES_STEP {
+        data.moles -= MOLES_STEP;
+    }
+    if data.gas != gas || data.model != model || data.moles != moles {
+        gas_model.0 = data.model.build(data.gas, data.moles);
+        data.handle_x = data.handle_x.max(get_min_handle_x(&gas_model));
+    }
+    if data.moles != moles {
+        // gas is let in or out at the same tempurature, so the pressure follows n
+        data.handle_y = get_handle_y(
+            gas_model
+                .0
+                .get_pressure(get_volume(data.handle_x), tempurature),
+        )
+        .clamp(
+            PLOT_POSITION.y - PLOT_HEIGHT / 2. + HANDLE_RADIUS,
+            PLOT_POSITION.y + PLOT_HEIGHT / 2. - HANDLE_RADIUS,
+        );
+    }
+    if data.gas != gas || data.model != model || data.moles != moles {
+        // energies under the old model don't compare with the new one, so the
+        // bookkeeping and the trace start over from the new state
+        data.reset();
+        *history = History::default();
+        *cycle = Cycle::default();
     }
 }
-fn move_isochoric(mut isobarics: Query<&mut Path, With<IsochoricLine>>, data: Res<Data>) {
-    for mut path in &mut isobarics {
-        let mut path_builder = PathBuilder::new();
-        path_builder.move_to(Vec2 {
-            x: data.handle_x,
-            y: PLOT_POSITION.y - PLOT_HEIGHT / 2.,
-        });
-        path_builder.line_to(Vec2 {
-            x: data.handle_x,
-            y: PLOT_POSITION.y + PLOT_HEIGHT / 2.,
-        });
-        *path = path_builder.build();
+
+fn handle_background_input(keys: Res<Input<KeyCode>>, mut background: ResMut<Background>) {
+    if keys.just_pressed(KeyCode::B) {
+        *background = match *background {
+            Background::Plain => Background::Contours,
+            Background::Contours => Background::Heatmap(Quantity::Tempurature),
+            Background::Heatmap(Quantity::Tempurature) => Background::Heatmap(Quantity::Energy),
+            Background::Heatmap(Quantity::Energy) => Background::Heatmap(Quantity::Entropy),
+            Background::Heatmap(_) => Background::Plain,
+        };
     }
 }
 
-fn move_isothermic(mut isothermics: Query<&mut Path, With<IsothermicLine>>, data: Res<Data>) {
-    for mut path in &mut isothermics {
-        let mut path_builder = PathBuilder::new();
-        path_builder.move_to(Vec2 {
-            x: data.handle_x,
-            y: data.handle_y,
-        });
-        for handle_y in data.handle_y as i64..=(PLOT_POSITION.y + PLOT_HEIGHT / 2.) as i64 {
-            path_builder.line_to(Vec2 {
-                x: get_handle_x(
-                    get_volume(data.handle_x) * get_pressure(data.handle_y)
-                        / get_pressure(handle_y as Scalar),
-                ),
-                y: handle_y as Scalar,
-            });
-        }
-        path_builder.move_to(Vec2 {
-            x: data.handle_x,
-            y: data.handle_y,
-        });
-        for handle_x in data.handle_x as i64..=(PLOT_POSITION.x + PLOT_WIDTH / 2.) as i64 {
-            path_builder.line_to(Vec2 {
-                x: handle_x as Scalar,
-                y: get_handle_y(
-                    get_pressure(data.handle_y) * get_volume(data.handle_x)
-                        / get_volume(handle_x as Scalar),
-                ),
-            });
-        }
-        *path = path_builder.build();
+fn handle_trace_input(
+    keys: Res<Input<KeyCode>>,
+    mut history: ResMut<History>,
+    mut cycle: ResMut<Cycle>,
+) {
+    if keys.just_pressed(KeyCode::Back) {
+        *history = History::default();
+        *cycle = Cycle::default();
     }
 }
 
-fn move_adiabatic(mut isothermics: Query<&mut Path, With<AdiabaticLine>>, data: Res<Data>) {
-    for mut path in &mut isothermics {
-        let mut path_builder = PathBuilder::new();
-        path_builder.move_to(Vec2 {
-            x: data.handle_x,
-            y: data.handle_y,
-        });
-        for handle_y in data.handle_y as i64..=(PLOT_POSITION.y + PLOT_HEIGHT / 2.) as i64 {
-            path_builder.line_to(Vec2 {
-                x: get_handle_x(
-                    (get_volume(data.handle_x).powf(GAMMA) * get_pressure(data.handle_y)
-                        / get_pressure(handle_y as Scalar))
-                    .powf(1. / GAMMA),
-                ),
-                y: handle_y as Scalar,
-            });
+fn handle_reservoir_input(keys: Res<Input<KeyCode>>, mut reservoirs: Query<&mut Reservoir>) {
+    for mut reservoir in &mut reservoirs {
+        let (attach, warmer, cooler) = match reservoir.name {
+            "hot" => (KeyCode::H, KeyCode::Up, KeyCode::Down),
+            _ => (KeyCode::C, KeyCode::Right, KeyCode::Left),
+        };
+        if keys.just_pressed(attach) {
+            reservoir.attached = !reservoir.attached;
         }
-        path_builder.move_to(Vec2 {
-            x: data.handle_x,
-            y: data.handle_y,
-        });
-        for handle_x in data.handle_x as i64..=(PLOT_POSITION.x + PLOT_WIDTH / 2.) as i64 {
-            path_builder.line_to(Vec2 {
-                x: handle_x as Scalar,
-                y: get_handle_y(
-                    get_pressure(data.handle_y) * get_volume(data.handle_x).powf(GAMMA)
-                        / get_volume(handle_x as Scalar).powf(GAMMA),
-                ),
-            });
+        if keys.just_pressed(warmer) {
+            reservoir.tempurature += RESERVOIR_STEP;
+        }
+        if keys.just_pressed(cooler) && reservoir.tempurature > RESERVOIR_STEP {
+            reservoir.tempurature -= RESERVOIR_STEP;
         }
-        *path = path_builder.build();
     }
 }
 
-fn update_tempurature_reading(
-    mut tempurature_readings: Query<&mut Text, With<TempuratureReading>>,
+// Attached reservoirs push heat through the wall in proportion to the
+// tempurature difference. The piston holds its volume while this happens,
+// unless dragging is locked to isobaric, in which case it holds the pressure.
+fn exchange_heat(
+    time: Res<Time>,
+    reservoirs: Query<&Reservoir>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    playback: Res<Playback>,
+    gas_model: Res<GasModel>,
+) {
+    // a playing lap decides the state by itself
+    if playback.playing {
+        return;
+    }
+    let tempurature = get_tempurature(&gas_model, data.handle_x, data.handle_y);
+    let heat: Scalar = reservoirs
+        .iter()
+        .filter(|reservoir| reservoir.attached)
+        .map(|reservoir| {
+            RESERVOIR_CONDUCTANCE * (reservoir.tempurature - tempurature) * time.delta_seconds()
+        })
+        .sum();
+    if heat == 0. {
+        return;
+    }
+    let (volume, pressure) = (get_volume(data.handle_x), get_pressure(data.handle_y));
+    let (low, high) = get_state_bounds(&gas_model);
+    let (process, low, high) = match data.process {
+        Process::Isobaric => (Process::Isobaric, low.x, high.x),
+        _ => (Process::Isochoric, low.y, high.y),
+    };
+    let Some((new_volume, new_pressure)) = process::get_heated_state(
+        gas_model.0.as_ref(),
+        process,
+        volume,
+        pressure,
+        heat,
+        low,
+        high,
+    ) else {
+        return;
+    };
+    let transfer = process::get_transfer(
+        gas_model.0.as_ref(),
+        process,
+        volume,
+        pressure,
+        new_volume,
+        new_pressure,
+    );
+    data.work += transfer.work;
+    data.leg_work += transfer.work;
+    data.reservoir_heat += transfer.heat;
+    data.handle_x = get_handle_x(new_volume);
+    data.handle_y = get_handle_y(new_pressure);
+
+    // the trace only gets a new point once the state has moved a pixel, so it
+    // doesn't grow every frame as the gas creeps towards equilibrium
+    if history.points.is_empty() {
+        history
+            .points
+            .push(TracePoint::start(time.elapsed_seconds(), volume, pressure));
+    }
+    let last = history.points[history.points.len() - 1];
+    if Vec2::new(get_handle_x(last.volume), get_handle_y(last.pressure))
+        .distance(Vec2::new(data.handle_x, data.handle_y))
+        >= 1.
+    {
+        let transfer = process::get_transfer(
+            gas_model.0.as_ref(),
+            process,
+            last.volume,
+            last.pressure,
+            new_volume,
+            new_pressure,
+        );
+        history.points.push(TracePoint {
+            time: time.elapsed_seconds(),
+            volume: new_volume,
+            pressure: new_pressure,
+            process,
+            work: transfer.work,
+            heat: transfer.heat,
+            heat_absorbed: transfer.heat_absorbed,
+        });
+    }
+}
+
+fn handle_preset_input(
+    keys: Res<Input<KeyCode>>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    mut cycle: ResMut<Cycle>,
+    mut preset_cycle: ResMut<PresetCycle>,
+    mut playback: ResMut<Playback>,
+    gas_model: Res<GasModel>,
+) {
+    let mut rebuild = gas_model.is_changed();
+    for (key, preset) in [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+    ]
+    .into_iter()
+    .zip(Preset::ALL)
+    {
+        if keys.just_pressed(key) {
+            preset_cycle.preset = Some(preset);
+            preset_cycle.parameters = preset.default_parameters();
+            preset_cycle.parameter = Parameter::ALL
+                .into_iter()
+                .find(|parameter| preset.uses(*parameter))
+                .unwrap();
+            rebuild = true;
+        }
+    }
+    if keys.just_pressed(KeyCode::Key0) {
+        preset_cycle.preset = None;
+        rebuild = true;
+    }
+    let Some(preset) = preset_cycle.preset else {
+        if rebuild
+            && preset_cycle
+                .legs
+                .as_ref()
+                .map_or(true, |legs| !legs.is_empty())
+        {
+            preset_cycle.legs = Ok(Vec::new());
+            playback.playing = false;
+            playback.lap.clear();
+        }
+        return;
+    };
+    if keys.just_pressed(KeyCode::Tab) {
+        let parameters = Parameter::ALL
+            .into_iter()
+            .filter(|parameter| preset.uses(*parameter))
+            .collect::<Vec<_>>();
+        let index = parameters
+            .iter()
+            .position(|parameter| *parameter == preset_cycle.parameter)
+            .map_or(0, |index| (index + 1) % parameters.len());
+        preset_cycle.parameter = parameters[index];
+    }
+    let parameter = preset_cycle.parameter;
+    if preset.uses(parameter) {
+        let value = preset_cycle.parameters.get_mut(parameter);
+        if keys.just_pressed(KeyCode::Period) {
+            *value += parameter.step();
+            rebuild = true;
+        }
+        if keys.just_pressed(KeyCode::Comma) && *value > parameter.step() {
+            *value -= parameter.step();
+            rebuild = true;
+        }
+    }
+    if !rebuild {
+        return;
+    }
+
+    let (low, high) = get_state_bounds(&gas_model);
+    preset_cycle.legs = preset::fit(
+        preset,
+        gas_model.0.as_ref(),
+        &preset_cycle.parameters,
+        (low.x, low.y),
+        (high.x, high.y),
+    );
+    playback.playing = false;
+    playback.lap.clear();
+    if let Ok(legs) = &preset_cycle.legs {
+        data.handle_x = get_handle_x(legs[0].volume);
+        data.handle_y = get_handle_y(legs[0].pressure);
+        data.reset();
+        *history = History::default();
+        *cycle = Cycle::default();
+        playback.load(build_preset_lap(legs, &gas_model));
+        playback.playing = true;
+    }
+}
+
+// the preset's legs as a lap of trace points, PRESET_SAMPLES to a leg
+fn build_preset_lap(legs: &[Leg], gas_model: &GasModel) -> Vec<TracePoint> {
+    let mut lap = vec![TracePoint::start(0., legs[0].volume, legs[0].pressure)];
+    for (i, leg) in legs.iter().enumerate() {
+        for j in 1..=PRESET_SAMPLES {
+            let t = j as Scalar / PRESET_SAMPLES as Scalar;
+            let last = lap[lap.len() - 1];
+            let (volume, pressure) = process::get_state(
+                gas_model.0.as_ref(),
+                leg.process,
+                leg.volume,
+                leg.pressure,
+                leg.new_volume,
+                leg.new_pressure,
+                t,
+            );
+            let transfer = process::get_transfer(
+                gas_model.0.as_ref(),
+                leg.process,
+                last.volume,
+                last.pressure,
+                volume,
+                pressure,
+            );
+            lap.push(TracePoint {
+                time: (i as Scalar + t) / PRESET_SPEED,
+                volume,
+                pressure,
+                process: leg.process,
+                work: transfer.work,
+                heat: transfer.heat,
+                heat_absorbed: transfer.heat_absorbed,
+            });
+        }
+    }
+    lap
+}
+
+fn handle_playback_input(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    mut cycle: ResMut<Cycle>,
+    mut playback: ResMut<Playback>,
+    gas_model: Res<GasModel>,
+) {
+    if playback.lap.is_empty() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Z) {
+        playback.speed = (playback.speed / 2.).max(MIN_PLAYBACK_SPEED);
+    }
+    if keys.just_pressed(KeyCode::X) {
+        playback.speed = (playback.speed * 2.).min(MAX_PLAYBACK_SPEED);
+    }
+    if !keys.just_pressed(KeyCode::Space) && !keys.just_pressed(KeyCode::N) {
+        return;
+    }
+    // the handle may have been moved off the lap since it was paused
+    let point = playback.lap[playback.index];
+    let handle = Vec2::new(get_handle_x(point.volume), get_handle_y(point.pressure));
+    if handle.distance(Vec2::new(data.handle_x, data.handle_y)) > 0.5 {
+        data.handle_x = handle.x;
+        data.handle_y = handle.y;
+        data.start_leg();
+        *history = History::default();
+        cycle.start = 0;
+    }
+    if keys.just_pressed(KeyCode::Space) {
+        playback.playing = !playback.playing;
+    }
+    if keys.just_pressed(KeyCode::N) {
+        // on to the start of the next leg
+        playback.playing = false;
+        loop {
+            advance_playback(
+                &mut playback,
+                &mut data,
+                &mut history,
+                &mut cycle,
+                &gas_model,
+                time.elapsed_seconds(),
+            );
+            if playback.leg_starts.contains(&playback.index) {
+                break;
+            }
+        }
+        playback.clock = playback.lap[playback.index].time - playback.lap[0].time;
+    }
+}
+
+fn play_cycle(
+    time: Res<Time>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    mut cycle: ResMut<Cycle>,
+    mut playback: ResMut<Playback>,
+    gas_model: Res<GasModel>,
+) {
+    let duration = playback.get_duration();
+    if !playback.playing || duration <= 0. {
+        return;
+    }
+    playback.clock += playback.speed * time.delta_seconds();
+    while playback.lap[playback.index + 1].time - playback.lap[0].time <= playback.clock {
+        advance_playback(
+            &mut playback,
+            &mut data,
+            &mut history,
+            &mut cycle,
+            &gas_model,
+            time.elapsed_seconds(),
+        );
+        if playback.index == 0 {
+            playback.clock -= duration;
+        }
+    }
+}
+
+// Moves the handle on to the next point of the lap, along the process it was
+// reached by. Each new lap also starts a fresh trace, so the trace only ever
+// shows the lap being played.
+fn advance_playback(
+    playback: &mut Playback,
+    data: &mut Data,
+    history: &mut History,
+    cycle: &mut Cycle,
+    gas_model: &GasModel,
+    time: Scalar,
+) {
+    if playback.index == 0 {
+        let start = history.points.len().saturating_sub(1);
+        history.points.drain(..start);
+        history.leg_start = 0;
+        cycle.start = 0;
+    }
+    playback.index += 1;
+    let point = playback.lap[playback.index];
+    move_state(
+        data,
+        history,
+        gas_model,
+        point.process,
+        Vec2::new(get_handle_x(point.volume), get_handle_y(point.pressure)),
+        time,
+    );
+    if playback.index == playback.lap.len() - 1 {
+        playback.index = 0;
+    }
+    if playback.leg_starts.contains(&playback.index) {
+        data.start_leg();
+        history.leg_start = history.points.len() - 1;
+    }
+}
+
+fn is_typing(state_entry: Res<StateEntry>) -> bool {
+    state_entry.typing
+}
+
+// Enter opens the panel and submits it, Escape closes it. A typed state is
+// reached along the drag process, or as near to it as that process gets.
+#[allow(clippy::too_many_arguments)]
+fn handle_state_entry(
+    keys: Res<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    time: Res<Time>,
+    mut data: ResMut<Data>,
+    mut history: ResMut<History>,
+    mut playback: ResMut<Playback>,
+    mut state_entry: ResMut<StateEntry>,
+    gas_model: Res<GasModel>,
+) {
+    if !state_entry.typing {
+        characters.clear();
+        if keys.just_pressed(KeyCode::Return) {
+            state_entry.typing = true;
+            state_entry.text.clear();
+            state_entry.message.clear();
+        }
+        return;
+    }
+    for character in characters.iter() {
+        if character.char.is_ascii_alphanumeric() || " .,=-".contains(character.char) {
+            state_entry.text.push(character.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        state_entry.text.pop();
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        state_entry.typing = false;
+    }
+    if !keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+    state_entry.typing = false;
+    let (low, high) = get_state_bounds(&gas_model);
+    let target = match entry::parse(&state_entry.text).and_then(|values| {
+        entry::get_state(
+            gas_model.0.as_ref(),
+            values,
+            (low.x, low.y),
+            (high.x, high.y),
+        )
+    }) {
+        Ok(target) => target,
+        Err(message) => {
+            state_entry.message = message.to_string();
+            return;
+        }
+    };
+    let target_handle = Vec2::new(get_handle_x(target.0), get_handle_y(target.1));
+    let new_handle = project_onto_process(&data, &gas_model, target_handle);
+
+    playback.playing = false;
+    playback.lap.clear();
+    data.start_leg();
+    history.leg_start = history.points.len().saturating_sub(1);
+    let process = data.process;
+    let (volume, pressure) = (get_volume(data.handle_x), get_pressure(data.handle_y));
+    for i in 1..=STATE_ENTRY_STEPS {
+        let (volume_t, pressure_t) = process::get_state(
+            gas_model.0.as_ref(),
+            process,
+            volume,
+            pressure,
+            get_volume(new_handle.x),
+            get_pressure(new_handle.y),
+            i as Scalar / STATE_ENTRY_STEPS as Scalar,
+        );
+        move_state(
+            &mut data,
+            &mut history,
+            &gas_model,
+            process,
+            Vec2::new(get_handle_x(volume_t), get_handle_y(pressure_t)),
+            time.elapsed_seconds(),
+        );
+    }
+    state_entry.message = format!(
+        "{} P = {:.2} Pa, V = {:.2} m^3, T = {:.1} K",
+        if new_handle.distance(target_handle) > 0.5 {
+            format!("nearest {}:", process.name())
+        } else {
+            "moved to".to_string()
+        },
+        get_pressure(data.handle_y),
+        get_volume(data.handle_x),
+        get_tempurature(&gas_model, data.handle_x, data.handle_y)
+    );
+}
+
+fn detect_cycle(
+    history: Res<History>,
+    gas_model: Res<GasModel>,
+    mut cycle: ResMut<Cycle>,
+    mut playback: ResMut<Playback>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+    let lap = &history.points[cycle.start.min(history.points.len())..];
+    if cycle::is_closed(
+        lap,
+        HANDLE_RADIUS / VOLUME_SCALE,
+        HANDLE_RADIUS / PRESSURE_SCALE,
+    ) {
+        cycle.summary = Some(cycle::summarize(lap, gas_model.0.as_ref()));
+        cycle.points = lap.to_vec();
+        cycle.start = history.points.len() - 1;
+        if playback.lap.is_empty() {
+            // ready to play from where the handle is, so the first move
+            // closes the lap the same way the summary does
+            let spacing = (lap[lap.len() - 1].time - lap[0].time) / (lap.len() - 1) as Scalar;
+            playback.load(cycle::close(lap, gas_model.0.as_ref(), spacing));
+            playback.index = playback.lap.len() - 2;
+            playback.clock = playback.lap[playback.index].time - playback.lap[0].time;
+        }
+    }
+}
+
+fn move_handle(mut handles: Query<&mut Transform, With<Handle>>, data: Res<Data>) {
+    for mut transform in &mut handles {
+        transform.translation.x = data.handle_x;
+        transform.translation.y = data.handle_y;
+    }
+}
+
+// The piston is driven by its velocity rather than placed, so the particles
+// that bounce off it while it moves gain or lose energy.
+fn move_piston(
+    mut pistons: Query<(&Position, &mut LinearVelocity), With<Piston>>,
+    data: Res<Data>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds().min(PHYSICS_MAX_DT);
+    for (position, mut velocity) in &mut pistons {
+        velocity.x = if dt > 0. {
+            (get_piston_x(&data) - position.x) / dt
+        } else {
+            0.
+        };
+    }
+}
+
+fn get_random_particle_position(rng: &mut impl Rng, data: &Data) -> Vec2 {
+    Vec2 {
+        x: rng.gen_range(PARTICLE_LEFT..get_particle_right(data)),
+        y: rng.gen_range(
+            BOX_POSITION.y - PARTICLE_HEIGHT / 2. ..BOX_POSITION.y + PARTICLE_HEIGHT / 2.,
+        ),
+    }
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    particle_assets: &ParticleAssets,
+    position: Vec2,
+    velocity: Vec2,
+) {
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: particle_assets.mesh.clone(),
+            material: particle_assets.material.clone(),
+            ..default()
+        },
+        Collider::ball(PARTICLE_RADIUS),
+        RigidBody::Dynamic,
+        Position(position),
+        Restitution::new(1.),
+        Friction::new(0.),
+        LinearVelocity(velocity),
+        LastVelocity(velocity),
+        Particle,
+    ));
+}
+
+fn fix_particles_count(
+    mut commands: Commands,
+    particles: Query<Entity, With<Particle>>,
+    particle_assets: Res<ParticleAssets>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    let count = particles.iter().len();
+    let target = (data.moles * PARTICLES_PER_MOLE).round() as usize;
+    for entity in particles.iter().skip(target) {
+        commands.entity(entity).despawn();
+    }
+    // new gas comes in at the tempurature of the state
+    let thermal_speed = get_particle_scale(&data).get_thermal_speed(get_tempurature(
+        &gas_model,
+        data.handle_x,
+        data.handle_y,
+    ));
+    let mut rng = rand::thread_rng();
+    for _ in count..target {
+        spawn_particle(
+            &mut commands,
+            &particle_assets,
+            get_random_particle_position(&mut rng, &data),
+            Vec2::new(
+                get_normal(&mut rng) * thermal_speed,
+                get_normal(&mut rng) * thermal_speed,
+            ),
+        );
+    }
+}
+
+// Particles that got through a wall are put back on its inner side, heading
+// inwards with the same speed.
+fn fix_particles_location(
+    mut particles: Query<(&mut Position, &mut LinearVelocity), With<Particle>>,
+    data: Res<Data>,
+) {
+    let (left, right) = (PARTICLE_LEFT, get_particle_right(&data));
+    let (bottom, top) = (
+        BOX_POSITION.y - PARTICLE_HEIGHT / 2.,
+        BOX_POSITION.y + PARTICLE_HEIGHT / 2.,
+    );
+    for (mut position, mut velocity) in &mut particles {
+        if position.x < left - PARTICLE_RADIUS {
+            position.x = left;
+            velocity.x = velocity.x.abs();
+        } else if position.x > right + PARTICLE_RADIUS {
+            position.x = right.max(left);
+            velocity.x = -velocity.x.abs();
+        }
+        if position.y < bottom - PARTICLE_RADIUS {
+            position.y = bottom;
+            velocity.y = velocity.y.abs();
+        } else if position.y > top + PARTICLE_RADIUS {
+            position.y = top;
+            velocity.y = -velocity.y.abs();
+        }
+    }
+}
+
+fn fix_particles_energy(
+    mut collisions: EventReader<CollisionStarted>,
+    mut particles: Query<(&Position, &mut LinearVelocity), With<Particle>>,
+    heated_walls: Query<(), With<HeatedWall>>,
+    reservoirs: Query<(&Reservoir, &Transform)>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+    time: Res<Time>,
+) {
+    let particle_scale = get_particle_scale(&data);
+    let tempurature = get_tempurature(&gas_model, data.handle_x, data.handle_y);
+    let thermal_speed = particle_scale.get_thermal_speed(tempurature);
+    let mut rng = rand::thread_rng();
+    let mut current_energy = 0.;
+    for (_, velocity) in &particles {
+        current_energy += particle_scale.get_particle_energy(velocity.length());
+    }
+    let scale = match data.thermostat {
+        Thermostat::Rescale => {
+            (particle_scale.get_kinetic_energy(tempurature) / current_energy).sqrt()
+        }
+        Thermostat::Berendsen => (1.
+            + time.delta_seconds() / BERENDSEN_TIME
+                * (tempurature / particle_scale.get_tempurature(current_energy) - 1.))
+            .max(0.)
+            .sqrt(),
+        Thermostat::Andersen => {
+            let chance = ANDERSEN_RATE * time.delta_seconds();
+            for (_, mut velocity) in &mut particles {
+                if rng.gen::<Scalar>() < chance {
+                    velocity.0 = Vec2::new(
+                        get_normal(&mut rng) * thermal_speed,
+                        get_normal(&mut rng) * thermal_speed,
+                    );
+                }
+            }
+            1.
+        }
+        Thermostat::Off => 1.,
+        Thermostat::HotWall => {
+            // the wall takes the tempurature of the reservoir attached where
+            // the particle hits it, and reflects it like any wall elsewhere.
+            // The speeds leaving a wall are weighted by how often they hit
+            // it, which makes the one across it Rayleigh distributed.
+            for CollisionStarted(entity1, entity2) in collisions.iter() {
+                for (particle, wall) in [(entity1, entity2), (entity2, entity1)] {
+                    if !heated_walls.contains(*wall) {
+                        continue;
+                    }
+                    let Ok((position, mut velocity)) = particles.get_mut(*particle) else {
+                        continue;
+                    };
+                    let Some((reservoir, _)) = reservoirs.iter().find(|(reservoir, transform)| {
+                        reservoir.attached
+                            && (position.y - transform.translation.y).abs() <= BOX_HEIGHT / 4.
+                    }) else {
+                        continue;
+                    };
+                    let thermal_speed = particle_scale.get_thermal_speed(reservoir.tempurature);
+                    velocity.0 = Vec2::new(
+                        (-2. * (1. - rng.gen::<Scalar>()).ln()).sqrt() * thermal_speed,
+                        get_normal(&mut rng) * thermal_speed,
+                    );
+                }
+            }
+            1.
+        }
+    };
+    if scale != 1. {
+        for (_, mut velocity) in &mut particles {
+            velocity.x *= scale;
+            velocity.y *= scale;
+        }
+    }
+}
+
+// a standard normal sample, by the Box-Muller transform
+fn get_normal(rng: &mut impl Rng) -> Scalar {
+    (-2. * (1. - rng.gen::<Scalar>()).ln()).sqrt()
+        * (2. * std::f32::consts::PI * rng.gen::<Scalar>()).cos()
+}
+
+// A particle hitting a wall hands it the momentum it loses across the wall,
+// m |v_in - v_out| along the normal. That holds for a moving piston and for
+// the hot wall's re-emission too, which is why this runs after the thermostat.
+fn measure_wall_impulses(
+    mut collisions: EventReader<CollisionStarted>,
+    mut particles: Query<(&LinearVelocity, &mut LastVelocity), With<Particle>>,
+    walls: Query<Option<&BoxFloorOrCeiling>, With<Wall>>,
+    mut gauge: ResMut<PressureGauge>,
+    time: Res<Time>,
+) {
+    let mut impulse = 0.;
+    for CollisionStarted(entity1, entity2) in collisions.iter() {
+        for (particle, wall) in [(entity1, entity2), (entity2, entity1)] {
+            if let (Ok((velocity, LastVelocity(last_velocity))), Ok(floor_or_ceiling)) =
+                (particles.get(*particle), walls.get(*wall))
+            {
+                let change = velocity.0 - *last_velocity;
+                let normal_change = if floor_or_ceiling.is_some() {
+                    change.y
+                } else {
+                    change.x
+                };
+                impulse += PARTICLE_MASS * normal_change.abs();
+            }
+        }
+    }
+    for (velocity, mut last_velocity) in &mut particles {
+        last_velocity.0 = velocity.0;
+    }
+    let now = time.elapsed_seconds();
+    gauge.impulses.push_back((now, impulse));
+    while gauge
+        .impulses
+        .front()
+        .is_some_and(|(time, _)| *time < now - GAUGE_WINDOW)
+    {
+        gauge.impulses.pop_front();
+    }
+}
+
+// (Pa, Pa), the pressure on the walls measured from the particles' impulses,
+// and its standard error across the bins of the window, once the window is full
+fn get_measured_pressure(
+    gauge: &PressureGauge,
+    data: &Data,
+    now: Scalar,
+) -> Option<(Scalar, Scalar)> {
+    let bin = GAUGE_WINDOW / GAUGE_BINS as Scalar;
+    if gauge.impulses.front()?.0 > now - GAUGE_WINDOW + bin {
+        return None;
+    }
+    let mut bins = [0.; GAUGE_BINS];
+    for (time, impulse) in &gauge.impulses {
+        bins[(((now - time) / bin) as usize).min(GAUGE_BINS - 1)] += impulse;
+    }
+    let perimeter = 2. * (get_particle_right(data) - PARTICLE_LEFT + PARTICLE_HEIGHT);
+    let particle_scale = get_particle_scale(data);
+    let pressures = bins.map(|impulse| particle_scale.get_pressure(impulse, bin, perimeter));
+    let count = GAUGE_BINS as Scalar;
+    let mean = pressures.iter().sum::<Scalar>() / count;
+    let variance = pressures
+        .iter()
+        .map(|pressure| (pressure - mean).powi(2))
+        .sum::<Scalar>()
+        / (count - 1.);
+    Some((mean, (variance / count).sqrt()))
+}
+
+fn move_reservoirs(mut reservoirs: Query<(&Reservoir, &mut Transform)>) {
+    for (reservoir, mut transform) in &mut reservoirs {
+        transform.translation.x = BOX_POSITION.x
+            - BOX_WIDTH / 2.
+            - RESERVOIR_WIDTH / 2.
+            - if reservoir.attached {
+                0.
+            } else {
+                RESERVOIR_GAP
+            };
+    }
+}
+
+fn move_preset_lines(
+    mut lines: Query<(&PresetLine, &mut Path, &mut Stroke)>,
+    preset_cycle: Res<PresetCycle>,
+    gas_model: Res<GasModel>,
+) {
+    if !preset_cycle.is_changed() && !gas_model.is_changed() {
+        return;
+    }
+    for (PresetLine(index), mut path, mut stroke) in &mut lines {
+        let mut path_builder = PathBuilder::new();
+        if let Some(leg) = preset_cycle
+            .legs
+            .as_ref()
+            .ok()
+            .and_then(|legs| legs.get(*index))
+        {
+            stroke.color = get_process_color(leg.process);
+            for i in 0..=SIDE_PLOT_SAMPLES {
+                let (volume, pressure) = process::get_state(
+                    gas_model.0.as_ref(),
+                    leg.process,
+                    leg.volume,
+                    leg.pressure,
+                    leg.new_volume,
+                    leg.new_pressure,
+                    i as Scalar / SIDE_PLOT_SAMPLES as Scalar,
+                );
+                let handle_position = Vec2::new(get_handle_x(volume), get_handle_y(pressure));
+                if i == 0 {
+                    path_builder.move_to(handle_position);
+                } else {
+                    path_builder.line_to(handle_position);
+                }
+            }
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn move_box_floor_and_ceiling(
+    mut walls: Query<&mut Transform, With<BoxFloorOrCeiling>>,
     data: Res<Data>,
 ) {
+    for mut transform in &mut walls {
+        transform.scale.x = (get_piston_x(&data) + BOX_THICKNESS / 2.
+            - (BOX_POSITION.x - BOX_WIDTH / 2.))
+            / BOX_WIDTH;
+        transform.translation.x =
+            (get_piston_x(&data) + BOX_THICKNESS / 2. + BOX_POSITION.x - BOX_WIDTH / 2.) / 2.;
+    }
+}
+
+fn move_process_lines(
+    mut lines: Query<(&ProcessLine, &mut Path)>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    for (ProcessLine(process), mut path) in &mut lines {
+        *path = match get_curve_process(&data, *process) {
+            Process::Isochoric => {
+                let mut path_builder = PathBuilder::new();
+                path_builder.move_to(Vec2 {
+                    x: data.handle_x,
+                    y: PLOT_POSITION.y - PLOT_HEIGHT / 2.,
+                });
+                path_builder.line_to(Vec2 {
+                    x: data.handle_x,
+                    y: PLOT_POSITION.y + PLOT_HEIGHT / 2.,
+                });
+                path_builder.build()
+            }
+            process => build_plot_curve(|handle_x| {
+                get_handle_y(
+                    process
+                        .get_pressure(
+                            gas_model.0.as_ref(),
+                            get_volume(data.handle_x),
+                            get_pressure(data.handle_y),
+                            get_volume(handle_x),
+                        )
+                        .unwrap_or(Scalar::NAN),
+                )
+            }),
+        };
+    }
+}
+
+// Isotherms evenly spaced in T and adiabats evenly spaced in S across the
+// reachable region. Each adiabat is found from where it crosses the right
+// edge, since S only grows with pressure along it.
+fn move_background_curves(
+    mut curves: Query<(&BackgroundCurve, &mut Path)>,
+    background: Res<Background>,
+    gas_model: Res<GasModel>,
+) {
+    if !background.is_changed() && !gas_model.is_changed() {
+        return;
+    }
+    let (_, high) = get_state_bounds(&gas_model);
+    for (BackgroundCurve(process), mut path) in &mut curves {
+        let mut path_builder = PathBuilder::new();
+        if *background == Background::Contours {
+            let quantity = match process {
+                Process::Isothermal => Quantity::Tempurature,
+                _ => Quantity::Entropy,
+            };
+            let (min, max) = get_quantity_range(&gas_model, quantity);
+            for i in 1..=BACKGROUND_CURVES {
+                let value = min + (max - min) * i as Scalar / (BACKGROUND_CURVES + 1) as Scalar;
+                match process {
+                    Process::Isothermal => add_plot_curve(&mut path_builder, |handle_x| {
+                        get_handle_y(gas_model.0.get_pressure(get_volume(handle_x), value))
+                    }),
+                    _ => {
+                        let (mut low_pressure, mut high_pressure) = (0., high.y);
+                        for _ in 0..32 {
+                            let pressure = (low_pressure + high_pressure) / 2.;
+                            if gas_model.0.get_entropy(high.x, pressure) < value {
+                                low_pressure = pressure;
+                            } else {
+                                high_pressure = pressure;
+                            }
+                        }
+                        add_plot_curve(&mut path_builder, |handle_x| {
+                            get_handle_y(gas_model.0.get_adiabatic_pressure(
+                                high.x,
+                                low_pressure,
+                                get_volume(handle_x),
+                            ))
+                        });
+                    }
+                }
+            }
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn move_heatmap(
+    mut heatmaps: Query<(&bevy::asset::Handle<Image>, &mut Visibility), With<Heatmap>>,
+    mut images: ResMut<Assets<Image>>,
+    background: Res<Background>,
+    gas_model: Res<GasModel>,
+) {
+    if !background.is_changed() && !gas_model.is_changed() {
+        return;
+    }
+    for (handle, mut visibility) in &mut heatmaps {
+        let Background::Heatmap(quantity) = *background else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        let Some(image) = images.get_mut(handle) else {
+            continue;
+        };
+        let (min, max) = get_quantity_range(&gas_model, quantity);
+        let (width, height) = (image.size().x as usize, image.size().y as usize);
+        let mut data = Vec::with_capacity(width * height * 4);
+        // rows run top to bottom in the image
+        for row in 0..height {
+            for column in 0..width {
+                let volume = get_volume(
+                    PLOT_POSITION.x - PLOT_WIDTH / 2.
+                        + (column as Scalar + 0.5) * HEATMAP_RESOLUTION,
+                );
+                let pressure = get_pressure(
+                    PLOT_POSITION.y + PLOT_HEIGHT / 2. - (row as Scalar + 0.5) * HEATMAP_RESOLUTION,
+                );
+                let t = (quantity.get(gas_model.0.as_ref(), volume, pressure) - min) / (max - min);
+                if volume <= gas_model.0.get_min_volume() || !t.is_finite() {
+                    data.extend([0, 0, 0, 0]);
+                } else {
+                    let t = t.clamp(0., 1.);
+                    data.extend([
+                        (30. + (153. - 30.) * t) as u8,
+                        (64. + (27. - 64.) * t) as u8,
+                        (175. + (27. - 175.) * t) as u8,
+                        96,
+                    ]);
+                }
+            }
+        }
+        image.data = data;
+    }
+}
+
+fn build_trace_path(points: &[TracePoint]) -> PathBuilder {
+    let mut path_builder = PathBuilder::new();
+    for (i, point) in points.iter().enumerate() {
+        let handle_position = Vec2 {
+            x: get_handle_x(point.volume),
+            y: get_handle_y(point.pressure),
+        };
+        if i == 0 {
+            path_builder.move_to(handle_position);
+        } else {
+            path_builder.line_to(handle_position);
+        }
+    }
+    path_builder
+}
+
+fn move_trace(mut traces: Query<&mut Path, With<TraceLine>>, history: Res<History>) {
+    if !history.is_changed() {
+        return;
+    }
+    for mut path in &mut traces {
+        *path = build_trace_path(&history.points).build();
+    }
+}
+
+// The particles move in two dimensions, so at equilibrium their speeds follow
+// f(v) = v / s^2 exp(-v^2 / 2 s^2), with s the thermal speed along one
+// direction of the plane.
+fn move_speed_histogram(
+    mut histograms: Query<&mut Path, With<SpeedHistogram>>,
+    mut curves: Query<&mut Path, (With<SpeedCurve>, Without<SpeedHistogram>)>,
+    particles: Query<&LinearVelocity, With<Particle>>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    let count = particles.iter().len();
+    if count == 0 {
+        return;
+    }
+    let thermal_speed = get_particle_scale(&data).get_thermal_speed(get_tempurature(
+        &gas_model,
+        data.handle_x,
+        data.handle_y,
+    ));
+    let bin = SPEED_RANGE * thermal_speed / SPEED_BINS as Scalar;
+    let mut bins = [0; SPEED_BINS];
+    for velocity in &particles {
+        if let Some(count) = bins.get_mut((velocity.length() / bin) as usize) {
+            *count += 1;
+        }
+    }
+    let corner = SPEED_PLOT_POSITION - Vec2::new(SPEED_PLOT_WIDTH, SPEED_PLOT_HEIGHT) / 2.;
+    // the plot's height is a probability density of 1 / s, above the peak
+    // of the distribution at v = s
+    let get_position = |speed: Scalar, density: Scalar| {
+        corner
+            + Vec2::new(
+                speed / (SPEED_RANGE * thermal_speed) * SPEED_PLOT_WIDTH,
+                (density * thermal_speed).min(1.) * SPEED_PLOT_HEIGHT,
+            )
+    };
+    for mut path in &mut histograms {
+        let mut path_builder = PathBuilder::new();
+        for (i, bin_count) in bins.iter().enumerate() {
+            if *bin_count == 0 {
+                continue;
+            }
+            let density = *bin_count as Scalar / (count as Scalar * bin);
+            path_builder.move_to(get_position(i as Scalar * bin, 0.));
+            path_builder.line_to(get_position(i as Scalar * bin, density));
+            path_builder.line_to(get_position((i + 1) as Scalar * bin, density));
+            path_builder.line_to(get_position((i + 1) as Scalar * bin, 0.));
+            path_builder.close();
+        }
+        *path = path_builder.build();
+    }
+    for mut path in &mut curves {
+        let mut path_builder = PathBuilder::new();
+        for i in 0..=SPEED_SAMPLES {
+            let speed = SPEED_RANGE * thermal_speed * i as Scalar / SPEED_SAMPLES as Scalar;
+            let density =
+                speed / thermal_speed.powi(2) * (-(speed / thermal_speed).powi(2) / 2.).exp();
+            if i == 0 {
+                path_builder.move_to(get_position(speed, density));
+            } else {
+                path_builder.line_to(get_position(speed, density));
+            }
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn update_plot_axes(mut plot_axes: Query<&mut PlotAxes>, gas_model: Res<GasModel>) {
+    if !gas_model.is_changed() {
+        return;
+    }
+    for mut axes in &mut plot_axes {
+        *axes = PlotAxes::new(&axes.plot, &gas_model);
+    }
+}
+
+fn move_plot_markers(
+    mut markers: Query<(&PlotAxes, &mut Transform), With<PlotMarker>>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    for (axes, mut transform) in &mut markers {
+        let position = axes.get_position(
+            &gas_model,
+            get_volume(data.handle_x),
+            get_pressure(data.handle_y),
+        );
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+// the family of process curves through the current state, as far as they
+// stay inside the region the handle can reach
+fn move_plot_curves(
+    mut curves: Query<(&PlotAxes, &PlotCurve, &mut Path)>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    let (low, high) = get_state_bounds(&gas_model);
+    let (volume, pressure) = (get_volume(data.handle_x), get_pressure(data.handle_y));
+    for (axes, PlotCurve(process), mut path) in &mut curves {
+        let process = get_curve_process(&data, *process);
+        let mut path_builder = PathBuilder::new();
+        let mut drawing = false;
+        for i in 0..=SIDE_PLOT_SAMPLES {
+            let t = i as Scalar / SIDE_PLOT_SAMPLES as Scalar;
+            let (volume_t, pressure_t) = match process {
+                Process::Isochoric => (volume, low.y + (high.y - low.y) * t),
+                process => {
+                    let volume_t = low.x + (high.x - low.x) * t;
+                    (
+                        volume_t,
+                        process
+                            .get_pressure(gas_model.0.as_ref(), volume, pressure, volume_t)
+                            .unwrap_or(Scalar::NAN),
+                    )
+                }
+            };
+            if (low.y..=high.y).contains(&pressure_t) {
+                let position = axes.get_position(&gas_model, volume_t, pressure_t);
+                if drawing {
+                    path_builder.line_to(position);
+                } else {
+                    path_builder.move_to(position);
+                }
+                drawing = true;
+            } else {
+                drawing = false;
+            }
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn move_plot_traces(
+    mut traces: Query<(&PlotAxes, &mut Path), With<PlotTrace>>,
+    history: Res<History>,
+    gas_model: Res<GasModel>,
+) {
+    if !history.is_changed() && !gas_model.is_changed() {
+        return;
+    }
+    for (axes, mut path) in &mut traces {
+        let mut path_builder = PathBuilder::new();
+        for (i, point) in history.points.iter().enumerate() {
+            let position = axes.get_position(&gas_model, point.volume, point.pressure);
+            if i == 0 {
+                path_builder.move_to(position);
+            } else {
+                path_builder.line_to(position);
+            }
+        }
+        *path = path_builder.build();
+    }
+}
+
+// the area under the current leg, which on T-S axes is the heat it took in
+fn move_plot_leg_areas(
+    mut areas: Query<(&PlotAxes, &mut Path), With<PlotLegArea>>,
+    history: Res<History>,
+    gas_model: Res<GasModel>,
+) {
+    if !history.is_changed() && !gas_model.is_changed() {
+        return;
+    }
+    let leg = &history.points[history.leg_start.min(history.points.len())..];
+    for (axes, mut path) in &mut areas {
+        let mut path_builder = PathBuilder::new();
+        if let (Some(first), Some(last)) = (leg.first(), leg.last()) {
+            path_builder.move_to(Vec2 {
+                x: axes
+                    .get_position(&gas_model, first.volume, first.pressure)
+                    .x,
+                y: axes.get_bottom(),
+            });
+            for point in leg {
+                path_builder.line_to(axes.get_position(&gas_model, point.volume, point.pressure));
+            }
+            path_builder.line_to(Vec2 {
+                x: axes.get_position(&gas_model, last.volume, last.pressure).x,
+                y: axes.get_bottom(),
+            });
+            path_builder.close();
+        }
+        *path = path_builder.build();
+    }
+}
+
+fn move_cycle_area(mut areas: Query<&mut Path, With<CycleArea>>, cycle: Res<Cycle>) {
+    if !cycle.is_changed() {
+        return;
+    }
+    for mut path in &mut areas {
+        let mut path_builder = build_trace_path(&cycle.points);
+        path_builder.close();
+        *path = path_builder.build();
+    }
+}
+
+fn update_tempurature_reading(
+    mut tempurature_readings: Query<&mut Text, With<TempuratureReading>>,
+    particles: Query<&LinearVelocity, With<Particle>>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+    gauge: Res<PressureGauge>,
+    time: Res<Time>,
+) {
+    let particle_scale = get_particle_scale(&data);
+    let kinetic_energy = particles
+        .iter()
+        .map(|velocity| particle_scale.get_particle_energy(velocity.length()))
+        .sum::<Scalar>();
     for mut text in &mut tempurature_readings {
+        let measured = match get_measured_pressure(&gauge, &data, time.elapsed_seconds()) {
+            Some((pressure, error)) => format!("{:.1} +/- {:.1} Pa", pressure, error),
+            None => "measuring".to_string(),
+        };
+        let energy = get_energy(&gas_model, data.handle_x, data.handle_y);
+        let energy_change = energy - get_energy(&gas_model, data.reference.x, data.reference.y);
+        let leg_energy_change = energy - get_energy(&gas_model, data.leg_start.x, data.leg_start.y);
+        let (work_label, heat_label, sign) = match data.work_convention {
+            WorkConvention::ByGas => ("W by gas", "Q = dU + W", 1.),
+            WorkConvention::OnGas => ("W on gas", "Q = dU - W", -1.),
+        };
         text.sections[0].value = format!(
-            "T = {} K\nW = {} J\nQ = {} J",
-            get_tempurature(data.handle_x, data.handle_y).round(),
-            data.work.round(),
-            (get_energy(data.handle_x, data.handle_y) + data.work).round()
+            "P = {:.1} Pa, on walls {}\nT = {} K (particles {} K), S = {} J/K\ndU = {} J (leg {} J)\n{} = {} J (leg {} J)\n{} = {} J (leg {} J)",
+            get_pressure(data.handle_y),
+            measured,
+            get_tempurature(&gas_model, data.handle_x, data.handle_y).round(),
+            particle_scale.get_tempurature(kinetic_energy).round(),
+            gas_model
+                .0
+                .get_entropy(get_volume(data.handle_x), get_pressure(data.handle_y))
+                .round(),
+            energy_change.round(),
+            leg_energy_change.round(),
+            work_label,
+            (sign * data.work).round(),
+            (sign * data.leg_work).round(),
+            heat_label,
+            (energy_change + data.work).round(),
+            (leg_energy_change + data.leg_work).round()
         );
     }
 }
 
+fn update_gas_reading(
+    mut gas_readings: Query<&mut Text, With<GasReading>>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    // left to the piston, the particles only follow the state on the plot when
+    // the gas heats as they do
+    let mismatch = if data.thermostat == Thermostat::Off && data.gas.degrees_of_freedom != 2. {
+        " (f = 2 only)"
+    } else {
+        ""
+    };
+    for mut text in &mut gas_readings {
+        text.sections[0].value = format!(
+            "{} mol {}, {}\n{} (f = {})\n{} particles, {} thermostat{}",
+            data.moles,
+            data.gas.name,
+            gas_model.0.name(),
+            data.gas.kind(),
+            data.gas.degrees_of_freedom,
+            data.dimensions.name(),
+            data.thermostat.name(),
+            mismatch
+        );
+    }
+}
+
+fn update_cycle_reading(
+    mut cycle_readings: Query<&mut Text, With<CycleReading>>,
+    cycle: Res<Cycle>,
+) {
+    for mut text in &mut cycle_readings {
+        text.sections[0].value = match cycle.summary {
+            Some(summary) if summary.is_reversed() => format!(
+                "reversed: refrigerator or heat pump\nW in = {} J\nQ from cold side = {} J at ~{} K\nQ to hot side = {} J at ~{} K\nCOP cooling = {:.2} (reversible {:.2})\nCOP heating = {:.2} (reversible {:.2})",
+                (-summary.net_work).round(),
+                summary.heat_absorbed.round(),
+                summary.absorbed_tempurature.round(),
+                summary.heat_rejected.round(),
+                summary.rejected_tempurature.round(),
+                summary.cooling_cop(),
+                summary.carnot_cooling_cop(),
+                summary.heating_cop(),
+                summary.carnot_heating_cop()
+            ),
+            Some(summary) => format!(
+                "W net = {} J\nQ in = {} J\nQ out = {} J\nefficiency = {}% (Carnot {}%)",
+                summary.net_work.round(),
+                summary.heat_absorbed.round(),
+                summary.heat_rejected.round(),
+                (summary.efficiency * 100.).round(),
+                (summary.carnot_efficiency * 100.).round()
+            ),
+            None => String::new(),
+        };
+    }
+}
+
+fn update_process_reading(
+    mut process_readings: Query<&mut Text, With<ProcessReading>>,
+    data: Res<Data>,
+) {
+    // C_n is the ideal gas's; van der Waals heat comes from its own energy
+    let heat_capacity = match data.model {
+        Model::VanDerWaals => String::new(),
+        Model::Ideal if (data.polytropic_exponent - 1.).abs() < 1e-6 => {
+            ", C_n infinite (isothermal)".to_string()
+        }
+        Model::Ideal => format!(
+            ", C_n = {:.1} J/mol K",
+            data.gas.cn(data.polytropic_exponent)
+        ),
+    };
+    for mut text in &mut process_readings {
+        text.sections[0].value = format!(
+            "drag: {} (F T P V S Y)\nn = {:.1} (J K){}",
+            data.process.name(),
+            data.polytropic_exponent,
+            heat_capacity
+        );
+    }
+}
+
+fn update_reservoir_reading(
+    mut reservoir_readings: Query<&mut Text, With<ReservoirReading>>,
+    reservoirs: Query<&Reservoir>,
+    data: Res<Data>,
+) {
+    for mut text in &mut reservoir_readings {
+        let mut value = String::new();
+        for reservoir in &reservoirs {
+            value += &format!(
+                "{} reservoir {} K{}\n",
+                reservoir.name,
+                reservoir.tempurature,
+                if reservoir.attached { ", attached" } else { "" }
+            );
+        }
+        value += &format!(
+            "Q from reservoirs = {} J\n(H C attach, arrows set T)",
+            data.reservoir_heat.round()
+        );
+        text.sections[0].value = value;
+    }
+}
+
+fn update_preset_reading(
+    mut preset_readings: Query<&mut Text, With<PresetReading>>,
+    preset_cycle: Res<PresetCycle>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    for mut text in &mut preset_readings {
+        let mut value = format!(
+            "preset: {} (0 off)\n",
+            preset_cycle.preset.map_or("none", |preset| preset.name())
+        );
+        for (i, preset) in Preset::ALL.into_iter().enumerate() {
+            value += &format!(
+                "{} {}{}",
+                i + 1,
+                preset.name(),
+                if i % 3 == 2 { "\n" } else { "  " }
+            );
+        }
+        value += "\n";
+        let Some(preset) = preset_cycle.preset else {
+            text.sections[0].value = value;
+            continue;
+        };
+        for parameter in Parameter::ALL {
+            if preset.uses(parameter) {
+                value += &format!(
+                    "{} {} = {}{}\n",
+                    if parameter == preset_cycle.parameter {
+                        ">"
+                    } else {
+                        " "
+                    },
+                    parameter.name(),
+                    preset_cycle.parameters.get(parameter),
+                    parameter.unit()
+                );
+            }
+        }
+        value += "(Tab pick, , . change)\n";
+        match &preset_cycle.legs {
+            Ok(legs) => {
+                let (work_label, sign) = match data.work_convention {
+                    WorkConvention::ByGas => ("W by", 1.),
+                    WorkConvention::OnGas => ("W on", -1.),
+                };
+                value += &format!(
+                    "\n  {:<10} {:>5} {:>5} {:>5}\n",
+                    "leg", "dU", work_label, "Q"
+                );
+                for (i, leg) in legs.iter().enumerate() {
+                    let energy_change = gas_model.0.get_energy(leg.new_volume, leg.new_pressure)
+                        - gas_model.0.get_energy(leg.volume, leg.pressure);
+                    let transfer = process::get_transfer(
+                        gas_model.0.as_ref(),
+                        leg.process,
+                        leg.volume,
+                        leg.pressure,
+                        leg.new_volume,
+                        leg.new_pressure,
+                    );
+                    value += &format!(
+                        "{} {:<10} {:>5} {:>5} {:>5}\n",
+                        i + 1,
+                        leg.process.name(),
+                        energy_change.round(),
+                        (sign * transfer.work).round(),
+                        transfer.heat.round()
+                    );
+                }
+            }
+            Err(error) => value += &format!("\n{}", error),
+        }
+        text.sections[0].value = value;
+    }
+}
+
+fn update_playback_reading(
+    mut playback_readings: Query<&mut Text, With<PlaybackReading>>,
+    playback: Res<Playback>,
+) {
+    for mut text in &mut playback_readings {
+        text.sections[0].value = if playback.lap.is_empty() {
+            "play: close a cycle first".to_string()
+        } else {
+            format!(
+                "play: {} at {}x (Space N Z X)",
+                if playback.playing { "on" } else { "paused" },
+                playback.speed
+            )
+        };
+    }
+}
+
+fn update_background_reading(
+    mut background_readings: Query<&mut Text, With<BackgroundReading>>,
+    background: Res<Background>,
+) {
+    for mut text in &mut background_readings {
+        text.sections[0].value = match *background {
+            Background::Plain => "background: plain (B)".to_string(),
+            Background::Contours => "background: T and S contours (B)".to_string(),
+            Background::Heatmap(quantity) => {
+                format!("background: {} heatmap (B)", quantity.symbol())
+            }
+        };
+    }
+}
+
+// The state under the cursor, and what the drag process would exchange getting
+// there. Off its curve, that is the point on the curve the handle would snap to.
+fn update_hover_tooltip(
+    mut tooltips: Query<
+        (&mut Text, &mut Transform, &mut Anchor, &mut Visibility),
+        With<HoverTooltip>,
+    >,
+    buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<Camera>>,
+    data: Res<Data>,
+    gas_model: Res<GasModel>,
+) {
+    let mouse_position = get_plot_cursor(&windows, &camera_q).filter(|mouse_position| {
+        !buttons.pressed(MouseButton::Left)
+            && get_volume(mouse_position.x) > gas_model.0.get_min_volume()
+    });
+    for (mut text, mut transform, mut anchor, mut visibility) in &mut tooltips {
+        let Some(mouse_position) = mouse_position else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        // opens away from the nearer side edge so it stays over the plot
+        if mouse_position.x < PLOT_POSITION.x {
+            *anchor = Anchor::BottomLeft;
+            transform.translation.x = mouse_position.x + HANDLE_RADIUS;
+        } else {
+            *anchor = Anchor::BottomRight;
+            transform.translation.x = mouse_position.x - HANDLE_RADIUS;
+        }
+        transform.translation.y = mouse_position.y + HANDLE_RADIUS;
+
+        let (volume, pressure) = (get_volume(mouse_position.x), get_pressure(mouse_position.y));
+        let target = project_onto_process(&data, &gas_model, mouse_position);
+        let transfer = process::get_transfer(
+            gas_model.0.as_ref(),
+            data.process,
+            get_volume(data.handle_x),
+            get_pressure(data.handle_y),
+            get_volume(target.x),
+            get_pressure(target.y),
+        );
+        let (work_label, sign) = match data.work_convention {
+            WorkConvention::ByGas => ("W by gas", 1.),
+            WorkConvention::OnGas => ("W on gas", -1.),
+        };
+        text.sections[0].value = format!(
+            "P = {:.1} Pa, V = {:.1} m^3\nT = {} K, S = {} J/K\nU = {} J, H = {} J\n{} {} = {} J, Q = {} J{}",
+            pressure,
+            volume,
+            gas_model.0.get_tempurature(volume, pressure).round(),
+            gas_model.0.get_entropy(volume, pressure).round(),
+            gas_model.0.get_energy(volume, pressure).round(),
+            gas_model.0.get_enthalpy(volume, pressure).round(),
+            data.process.name(),
+            work_label,
+            (sign * transfer.work).round(),
+            transfer.heat.round(),
+            if target.distance(mouse_position) > HANDLE_RADIUS {
+                " (nearest)"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+fn update_state_entry_reading(
+    mut state_entry_readings: Query<&mut Text, With<StateEntryReading>>,
+    state_entry: Res<StateEntry>,
+) {
+    for mut text in &mut state_entry_readings {
+        text.sections[0].value = if state_entry.typing {
+            format!("state: {}_\n(Enter go, Esc cancel)", state_entry.text)
+        } else {
+            format!("state: Enter to type P V T\n{}", state_entry.message)
+        };
+    }
+}
+
+fn spawn_plot(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    axes: PlotAxes,
+    shade_leg: bool,
+) {
+    let plot = axes.plot;
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes
+            .add(shape::Quad::new(Vec2::new(SIDE_PLOT_WIDTH, SIDE_PLOT_HEIGHT)).into())
+            .into(),
+        material: materials.add(ColorMaterial::from(Color::rgb(0.1, 0.1, 0.1))),
+        transform: Transform::from_translation(plot.position.extend(0.)),
+        ..default()
+    });
+    for process in PROCESS_CURVES {
+        commands.spawn((
+            ShapeBundle {
+                transform: Transform::from_xyz(0., 0., 0.1),
+                ..default()
+            },
+            Stroke::new(get_process_color(process), 3.0),
+            axes,
+            PlotCurve(process),
+        ));
+    }
+    if shade_leg {
+        commands.spawn((
+            ShapeBundle {
+                transform: Transform::from_xyz(0., 0., 0.25),
+                ..default()
+            },
+            Fill::color(Color::rgba_u8(245, 158, 11, 64)),
+            axes,
+            PlotLegArea,
+        ));
+    }
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.5),
+            ..default()
+        },
+        Stroke::new(get_process_color(Process::Line), 3.0),
+        axes,
+        PlotTrace,
+    ));
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(shape::Circle::new(HANDLE_RADIUS / 2.).into())
+                .into(),
+            material: materials.add(ColorMaterial::from(Color::rgb(0.2, 0.2, 0.2))),
+            transform: Transform::from_translation(plot.position.extend(1.)),
+            ..default()
+        },
+        axes,
+        PlotMarker,
+    ));
+    commands.spawn(Text2dBundle {
+        text: Text::from_section(
+            plot.y.symbol(),
+            TextStyle {
+                font_size: FONT_SIZE,
+                color: Color::ANTIQUE_WHITE,
+                ..default()
+            },
+        ),
+        transform: Transform::from_translation(Vec3 {
+            x: plot.position.x - SIDE_PLOT_WIDTH / 2. - TEXT_OFFSET,
+            y: plot.position.y,
+            z: 0.,
+        }),
+        text_anchor: Anchor::CenterRight,
+        ..default()
+    });
+    commands.spawn(Text2dBundle {
+        text: Text::from_section(
+            plot.x.symbol(),
+            TextStyle {
+                font_size: FONT_SIZE,
+                color: Color::ANTIQUE_WHITE,
+                ..default()
+            },
+        ),
+        transform: Transform::from_translation(Vec3 {
+            x: plot.position.x,
+            y: plot.position.y - SIDE_PLOT_HEIGHT / 2. - TEXT_OFFSET,
+            z: 0.,
+        }),
+        text_anchor: Anchor::TopCenter,
+        ..default()
+    });
+}
+
 fn setup(
     mut commands: Commands,
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
 ) {
-    commands.insert_resource(Data {
+    let data = Data {
         handle_x: PLOT_POSITION.x,
         handle_y: PLOT_POSITION.y,
         work: 0.,
+        reference: PLOT_POSITION,
+        leg_work: 0.,
+        leg_start: PLOT_POSITION,
+        reservoir_heat: 0.,
+        work_convention: WorkConvention::ByGas,
+        gas: Gas::HELIUM,
+        model: Model::Ideal,
+        moles: 1.,
+        process: Process::Line,
+        polytropic_exponent: 1.3,
+        dimensions: Dimensions::Three,
+        thermostat: Thermostat::Rescale,
+    };
+    let gas_model = GasModel(Model::Ideal.build(Gas::HELIUM, 1.));
+    let (piston_x, particle_right) = (get_piston_x(&data), get_particle_right(&data));
+    let thermal_speed = get_particle_scale(&data).get_thermal_speed(get_tempurature(
+        &gas_model,
+        data.handle_x,
+        data.handle_y,
+    ));
+    commands.insert_resource(data);
+    commands.insert_resource(History::default());
+    commands.insert_resource(Cycle::default());
+    commands.insert_resource(PresetCycle {
+        preset: None,
+        parameters: Preset::Carnot.default_parameters(),
+        parameter: Parameter::HotTempurature,
+        legs: Ok(Vec::new()),
+    });
+    commands.insert_resource(Background::Plain);
+    commands.insert_resource(StateEntry::default());
+    commands.insert_resource(PressureGauge::default());
+    commands.insert_resource(Playback {
+        lap: Vec::new(),
+        leg_starts: Vec::new(),
+        index: 0,
+        clock: 0.,
+        speed: 1.,
+        playing: false,
     });
 
-    // lines on plot
+    // background of plot
+    commands.spawn((
+        SpriteBundle {
+            texture: images.add(Image::new_fill(
+                Extent3d {
+                    width: (PLOT_WIDTH / HEATMAP_RESOLUTION) as u32,
+                    height: (PLOT_HEIGHT / HEATMAP_RESOLUTION) as u32,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[0, 0, 0, 0],
+                TextureFormat::Rgba8UnormSrgb,
+            )),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(PLOT_WIDTH, PLOT_HEIGHT)),
+                ..default()
+            },
+            transform: Transform::from_translation(PLOT_POSITION.extend(0.05)),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Heatmap,
+    ));
     commands.spawn((
-        ShapeBundle { ..default() },
-        Stroke::new(Color::rgb_u8(5, 46, 22), 5.0),
-        IsobaricLine,
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.1),
+            ..default()
+        },
+        Stroke::new(Color::rgba_u8(248, 113, 113, 64), 1.5),
+        BackgroundCurve(Process::Isothermal),
     ));
     commands.spawn((
-        ShapeBundle { ..default() },
-        Stroke::new(Color::rgb_u8(23, 37, 84), 5.0),
-        IsochoricLine,
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.1),
+            ..default()
+        },
+        Stroke::new(Color::rgba_u8(192, 132, 252, 64), 1.5),
+        BackgroundCurve(Process::Adiabatic),
     ));
+
+    // lines on plot
+    for process in PROCESS_CURVES {
+        commands.spawn((
+            ShapeBundle { ..default() },
+            Stroke::new(get_process_color(process), 5.0),
+            ProcessLine(process),
+        ));
+    }
     commands.spawn((
-        ShapeBundle { ..default() },
-        Stroke::new(Color::rgb_u8(69, 10, 10), 5.0),
-        IsothermicLine,
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.5),
+            ..default()
+        },
+        Stroke::new(Color::rgb_u8(245, 158, 11), 3.0),
+        TraceLine,
     ));
     commands.spawn((
-        ShapeBundle { ..default() },
-        Stroke::new(Color::rgb_u8(59, 7, 100), 5.0),
-        AdiabaticLine,
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.25),
+            ..default()
+        },
+        Fill::color(Color::rgba_u8(245, 158, 11, 64)),
+        CycleArea,
     ));
+    for index in 0..4 {
+        commands.spawn((
+            ShapeBundle {
+                transform: Transform::from_xyz(0., 0., 0.3),
+                ..default()
+            },
+            Stroke::new(Color::NONE, 8.0),
+            PresetLine(index),
+        ));
+    }
 
     // handle on plot
     commands.spawn((
@@ -484,6 +2842,23 @@ fn setup(
         text_anchor: Anchor::CenterLeft,
         ..default()
     });
+    commands.spawn(Text2dBundle {
+        text: Text::from_section(
+            "polytropic",
+            TextStyle {
+                font_size: FONT_SIZE,
+                color: Color::rgb_u8(8, 51, 68),
+                ..default()
+            },
+        ),
+        transform: Transform::from_translation(Vec3 {
+            x: PLOT_POSITION.x + PLOT_WIDTH / 2. + TEXT_OFFSET,
+            y: PLOT_POSITION.y + 5. * FONT_SIZE / 2.,
+            z: 0.,
+        }),
+        text_anchor: Anchor::CenterLeft,
+        ..default()
+    });
     commands.spawn((
         Text2dBundle {
             text: Text::from_section(
@@ -504,6 +2879,180 @@ fn setup(
         },
         TempuratureReading,
     ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(SIDEBAR_POSITION.extend(0.)),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        GasReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: SIDEBAR_POSITION.x,
+                y: SIDEBAR_POSITION.y - 3. * READING_FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        CycleReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: PLOT_POSITION.x - PLOT_WIDTH / 2.,
+                y: PLOT_POSITION.y - PLOT_HEIGHT / 2. - TEXT_OFFSET - FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        ProcessReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: PLOT_POSITION.x - PLOT_WIDTH / 2.,
+                y: PLOT_POSITION.y - PLOT_HEIGHT / 2. - TEXT_OFFSET - 3. * FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        PlaybackReading,
+    ));
+
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: SIDEBAR_POSITION.x,
+                y: SIDEBAR_POSITION.y - 10. * READING_FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        ReservoirReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: SIDEBAR_POSITION.x,
+                y: SIDEBAR_POSITION.y - 15. * READING_FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        PresetReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: SIDEBAR_POSITION.x,
+                y: SIDEBAR_POSITION.y - 30. * READING_FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        BackgroundReading,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_xyz(0., 0., 2.),
+            text_anchor: Anchor::BottomLeft,
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        HoverTooltip,
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: READING_FONT_SIZE,
+                    color: Color::ANTIQUE_WHITE,
+                    ..default()
+                },
+            ),
+            transform: Transform::from_translation(Vec3 {
+                x: SIDEBAR_POSITION.x,
+                y: SIDEBAR_POSITION.y - 32. * READING_FONT_SIZE,
+                z: 0.,
+            }),
+            text_anchor: Anchor::TopLeft,
+            ..default()
+        },
+        StateEntryReading,
+    ));
 
     // plot background
     commands.spawn(MaterialMesh2dBundle {
@@ -519,6 +3068,80 @@ fn setup(
         ..default()
     });
 
+    // side plots
+    for (i, (x, y, shade_leg)) in [
+        (Quantity::Entropy, Quantity::Tempurature, true),
+        (Quantity::Tempurature, Quantity::Pressure, false),
+        (Quantity::Volume, Quantity::Energy, false),
+        (Quantity::Entropy, Quantity::Enthalpy, false),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        spawn_plot(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            PlotAxes::new(
+                &Plot {
+                    x,
+                    y,
+                    position: Vec2 {
+                        x: SIDE_PLOT_X,
+                        y: 360. - 240. * i as Scalar,
+                    },
+                },
+                &gas_model,
+            ),
+            shade_leg,
+        );
+    }
+
+    commands.insert_resource(gas_model);
+
+    // speed histogram
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes
+            .add(shape::Quad::new(Vec2::new(SPEED_PLOT_WIDTH, SPEED_PLOT_HEIGHT)).into())
+            .into(),
+        material: materials.add(ColorMaterial::from(Color::rgb(0.1, 0.1, 0.1))),
+        transform: Transform::from_translation(SPEED_PLOT_POSITION.extend(0.)),
+        ..default()
+    });
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.1),
+            ..default()
+        },
+        Fill::color(Color::rgb(0.29, 0.33, 0.64)),
+        SpeedHistogram,
+    ));
+    commands.spawn((
+        ShapeBundle {
+            transform: Transform::from_xyz(0., 0., 0.2),
+            ..default()
+        },
+        Stroke::new(get_process_color(Process::Line), 3.0),
+        SpeedCurve,
+    ));
+    commands.spawn(Text2dBundle {
+        text: Text::from_section(
+            "|v|",
+            TextStyle {
+                font_size: FONT_SIZE,
+                color: Color::ANTIQUE_WHITE,
+                ..default()
+            },
+        ),
+        transform: Transform::from_translation(Vec3 {
+            x: SPEED_PLOT_POSITION.x,
+            y: SPEED_PLOT_POSITION.y - SPEED_PLOT_HEIGHT / 2. - TEXT_OFFSET,
+            z: 0.,
+        }),
+        text_anchor: Anchor::TopCenter,
+        ..default()
+    });
+
     // ceiling
     commands.spawn((
         MaterialMesh2dBundle {
@@ -540,6 +3163,7 @@ fn setup(
         Restitution::new(1.),
         Friction::new(0.),
         BoxFloorOrCeiling,
+        Wall,
     ));
     // floor
     commands.spawn((
@@ -562,8 +3186,9 @@ fn setup(
         Restitution::new(1.),
         Friction::new(0.),
         BoxFloorOrCeiling,
+        Wall,
     ));
-    // right Wall
+    // right wall, the piston
     commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshes
@@ -572,18 +3197,13 @@ fn setup(
             material: materials.add(ColorMaterial::from(Color::rgb(0.7, 0.7, 0.8))),
             ..default()
         },
-        RigidBody::Static,
-        Position(
-            BOX_POSITION
-                + Vec2 {
-                    x: (BOX_WIDTH - BOX_THICKNESS) / 2.,
-                    y: 0.,
-                },
-        ),
+        RigidBody::Kinematic,
+        Position(Vec2::new(piston_x, BOX_POSITION.y)),
         Collider::cuboid(BOX_THICKNESS, BOX_HEIGHT),
         Restitution::new(1.),
         Friction::new(0.),
         Piston,
+        Wall,
     ));
     // left wall
     commands.spawn((
@@ -605,41 +3225,83 @@ fn setup(
         Collider::cuboid(BOX_THICKNESS, BOX_HEIGHT),
         Restitution::new(1.),
         Friction::new(0.),
+        Wall,
+        HeatedWall,
     ));
 
+    // reservoirs, hot above cold, both detached
+    for (reservoir, color, y) in [
+        (
+            Reservoir {
+                name: "hot",
+                tempurature: 150.,
+                attached: false,
+            },
+            Color::rgb_u8(153, 27, 27),
+            BOX_HEIGHT / 4.,
+        ),
+        (
+            Reservoir {
+                name: "cold",
+                tempurature: 30.,
+                attached: false,
+            },
+            Color::rgb_u8(30, 64, 175),
+            -BOX_HEIGHT / 4.,
+        ),
+    ] {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(shape::Quad::new(Vec2::new(RESERVOIR_WIDTH, BOX_HEIGHT / 2.)).into())
+                    .into(),
+                material: materials.add(ColorMaterial::from(color)),
+                transform: Transform::from_translation(Vec3 {
+                    x: BOX_POSITION.x - BOX_WIDTH / 2. - RESERVOIR_WIDTH / 2. - RESERVOIR_GAP,
+                    y: BOX_POSITION.y + y,
+                    z: 0.,
+                }),
+                ..default()
+            },
+            reservoir,
+        ));
+    }
+
     // particles
+    let particle_assets = ParticleAssets {
+        mesh: meshes
+            .add(shape::Circle::new(PARTICLE_RADIUS).into())
+            .into(),
+        material: materials.add(ColorMaterial::from(Color::rgb(0.29, 0.33, 0.64))),
+    };
     let mut rng = rand::thread_rng();
     for x in -GRID_WIDTH_OUT..=GRID_WIDTH_OUT {
         for y in -GRID_HEIGHT_OUT..=GRID_HEIGHT_OUT {
-            commands.spawn((
-                MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(shape::Circle::new(PARTICLE_RADIUS).into())
-                        .into(),
-                    material: materials.add(ColorMaterial::from(Color::rgb(0.29, 0.33, 0.64))),
-                    ..default()
-                },
-                Collider::ball(PARTICLE_RADIUS),
-                RigidBody::Dynamic,
-                Position(
-                    BOX_POSITION
-                        + Vec2::new(
-                            x as Scalar * (BOX_WIDTH - 2. * BOX_THICKNESS - 3. * PARTICLE_RADIUS)
-                                / (GRID_WIDTH_OUT * 2) as Scalar,
-                            y as Scalar * (BOX_HEIGHT - 2. * BOX_THICKNESS - 3. * PARTICLE_RADIUS)
-                                / (GRID_HEIGHT_OUT * 2) as Scalar,
-                        ),
+            spawn_particle(
+                &mut commands,
+                &particle_assets,
+                Vec2::new((PARTICLE_LEFT + particle_right) / 2., BOX_POSITION.y)
+                    + Vec2::new(
+                        x as Scalar * (particle_right - PARTICLE_LEFT - PARTICLE_RADIUS)
+                            / (GRID_WIDTH_OUT * 2) as Scalar,
+                        y as Scalar * (PARTICLE_HEIGHT - PARTICLE_RADIUS)
+                            / (GRID_HEIGHT_OUT * 2) as Scalar,
+                    ),
+                // uniform in a square rather than Maxwellian, with the
+                // same spread, so the gas can be seen to relax
+                Vec2::new(
+                    rng.gen_range(-1.0..1.0) * Scalar::sqrt(3.) * thermal_speed,
+                    rng.gen_range(-1.0..1.0) * Scalar::sqrt(3.) * thermal_speed,
                 ),
-                Restitution::new(1.),
-                Friction::new(0.),
-                LinearVelocity(Vec2::new(
-                    rng.gen_range(-200.0..200.0),
-                    rng.gen_range(-200.0..200.0),
-                )),
-                Particle,
-            ));
+            );
         }
     }
+    commands.insert_resource(particle_assets);
 
-    commands.spawn(Camera2dBundle::default());
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMin {
+        min_width: VIEW_WIDTH,
+        min_height: VIEW_HEIGHT,
+    };
+    commands.spawn(camera);
 }
@@ -0,0 +1,621 @@
+use crate::{
+    process::Process,
+    thermo::{EquationOfState, Scalar},
+};
+
+// volumes are searched for between the molecules' own volume and this many
+// times the starting volume
+const SEARCH_RANGE: Scalar = 1e3;
+const SEARCH_STEPS: u32 = 48;
+const FIT_STEPS: u32 = 64;
+const FIT_TOLERANCE: Scalar = 1e-4; // relative, since shrinking only lands on the edge to rounding
+
+// A textbook cycle, built from a few parameters instead of dragged by hand.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Preset {
+    Carnot,
+    Otto,
+    Diesel,
+    Stirling,
+    Brayton,
+    Ericsson,
+    Atkinson,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 7] = [
+        Preset::Carnot,
+        Preset::Otto,
+        Preset::Diesel,
+        Preset::Stirling,
+        Preset::Brayton,
+        Preset::Ericsson,
+        Preset::Atkinson,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Carnot => "Carnot",
+            Preset::Otto => "Otto",
+            Preset::Diesel => "Diesel",
+            Preset::Stirling => "Stirling",
+            Preset::Brayton => "Brayton",
+            Preset::Ericsson => "Ericsson",
+            Preset::Atkinson => "Atkinson",
+        }
+    }
+
+    pub fn uses(&self, parameter: Parameter) -> bool {
+        match parameter {
+            Parameter::HotTempurature => *self != Preset::Diesel,
+            Parameter::ColdTempurature | Parameter::CompressionRatio => true,
+            Parameter::CutoffRatio => *self == Preset::Diesel,
+        }
+    }
+
+    // values that fit a mole of helium on the plot
+    pub fn default_parameters(&self) -> Parameters {
+        let (hot_tempurature, cold_tempurature, compression_ratio) = match self {
+            Preset::Carnot => (60., 40., 3.),
+            Preset::Otto | Preset::Diesel => (70., 25., 3.),
+            Preset::Stirling | Preset::Ericsson => (60., 30., 3.),
+            Preset::Brayton | Preset::Atkinson => (50., 25., 2.),
+        };
+        Parameters {
+            hot_tempurature,
+            cold_tempurature,
+            compression_ratio,
+            cutoff_ratio: 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Parameter {
+    HotTempurature,
+    ColdTempurature,
+    CompressionRatio,
+    CutoffRatio,
+}
+
+impl Parameter {
+    pub const ALL: [Parameter; 4] = [
+        Parameter::HotTempurature,
+        Parameter::ColdTempurature,
+        Parameter::CompressionRatio,
+        Parameter::CutoffRatio,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Parameter::HotTempurature => "T hot",
+            Parameter::ColdTempurature => "T cold",
+            Parameter::CompressionRatio => "compression",
+            Parameter::CutoffRatio => "cutoff",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Parameter::HotTempurature | Parameter::ColdTempurature => " K",
+            Parameter::CompressionRatio | Parameter::CutoffRatio => "",
+        }
+    }
+
+    // how much one key press changes it, which is also the smallest it goes
+    pub fn step(&self) -> Scalar {
+        match self {
+            Parameter::HotTempurature | Parameter::ColdTempurature => 5.,
+            Parameter::CompressionRatio | Parameter::CutoffRatio => 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Parameters {
+    pub hot_tempurature: Scalar,  // K
+    pub cold_tempurature: Scalar, // K
+    pub compression_ratio: Scalar,
+    pub cutoff_ratio: Scalar, // Diesel only, volume after over before the burn
+}
+
+impl Parameters {
+    pub fn get(&self, parameter: Parameter) -> Scalar {
+        match parameter {
+            Parameter::HotTempurature => self.hot_tempurature,
+            Parameter::ColdTempurature => self.cold_tempurature,
+            Parameter::CompressionRatio => self.compression_ratio,
+            Parameter::CutoffRatio => self.cutoff_ratio,
+        }
+    }
+
+    pub fn get_mut(&mut self, parameter: Parameter) -> &mut Scalar {
+        match parameter {
+            Parameter::HotTempurature => &mut self.hot_tempurature,
+            Parameter::ColdTempurature => &mut self.cold_tempurature,
+            Parameter::CompressionRatio => &mut self.compression_ratio,
+            Parameter::CutoffRatio => &mut self.cutoff_ratio,
+        }
+    }
+}
+
+// One leg of a cycle, between two states along a process.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Leg {
+    pub process: Process,
+    pub volume: Scalar,       // m^3
+    pub pressure: Scalar,     // Pa
+    pub new_volume: Scalar,   // m^3
+    pub new_pressure: Scalar, // Pa
+}
+
+// The legs of `preset`, starting from the cold state at `volume` before
+// compression. Compression ratios are taken over volume, except for Brayton
+// and Ericsson where they are taken over pressure.
+pub fn build(
+    preset: Preset,
+    gas_model: &dyn EquationOfState,
+    parameters: &Parameters,
+    volume: Scalar,
+) -> Result<Vec<Leg>, &'static str> {
+    let Parameters {
+        hot_tempurature: hot,
+        cold_tempurature: cold,
+        compression_ratio: ratio,
+        cutoff_ratio: cutoff,
+    } = *parameters;
+    if ratio <= 1. {
+        return Err("compression must be above 1");
+    }
+    if preset.uses(Parameter::HotTempurature) && hot <= cold {
+        return Err("T hot must be above T cold");
+    }
+    let low = gas_model.get_min_volume() * (1. + 1e-4) + 1e-6;
+    let high = volume * SEARCH_RANGE;
+    let tempurature = |state: (Scalar, Scalar)| gas_model.get_tempurature(state.0, state.1);
+    let isotherm =
+        |volume: Scalar, tempurature: Scalar| (volume, gas_model.get_pressure(volume, tempurature));
+    let adiabat = |state: (Scalar, Scalar), volume: Scalar| {
+        (
+            volume,
+            gas_model.get_adiabatic_pressure(state.0, state.1, volume),
+        )
+    };
+    // on the adiabat through `state`, where it reaches `target` tempurature
+    let adiabat_to = |state: (Scalar, Scalar), target: Scalar| {
+        adiabat(
+            state,
+            find_volume(
+                |volume| tempurature(adiabat(state, volume)),
+                target,
+                low,
+                high,
+            ),
+        )
+    };
+
+    let first = isotherm(volume, cold);
+    let (states, processes) = match preset {
+        Preset::Carnot => {
+            let third = isotherm(volume / ratio, hot);
+            let second = adiabat_to(third, cold);
+            if second.0 >= first.0 {
+                return Err("compression too small for the temperatures");
+            }
+            (
+                [first, second, third, adiabat_to(first, hot)],
+                [
+                    Process::Isothermal,
+                    Process::Adiabatic,
+                    Process::Isothermal,
+                    Process::Adiabatic,
+                ],
+            )
+        }
+        Preset::Otto | Preset::Atkinson => {
+            let second = adiabat(first, volume / ratio);
+            if tempurature(second) >= hot {
+                return Err("T hot must be above T after compression");
+            }
+            let third = isotherm(second.0, hot);
+            let (fourth, last) = match preset {
+                Preset::Otto => (adiabat(third, volume), Process::Isochoric),
+                _ => (
+                    adiabat(
+                        third,
+                        find_volume(|volume| adiabat(third, volume).1, first.1, low, high),
+                    ),
+                    Process::Isobaric,
+                ),
+            };
+            (
+                [first, second, third, fourth],
+                [
+                    Process::Adiabatic,
+                    Process::Isochoric,
+                    Process::Adiabatic,
+                    last,
+                ],
+            )
+        }
+        Preset::Diesel => {
+            if cutoff <= 1. || cutoff >= ratio {
+                return Err("cutoff must be between 1 and compression");
+            }
+            let second = adiabat(first, volume / ratio);
+            let third = (second.0 * cutoff, second.1);
+            (
+                [first, second, third, adiabat(third, volume)],
+                [
+                    Process::Adiabatic,
+                    Process::Isobaric,
+                    Process::Adiabatic,
+                    Process::Isochoric,
+                ],
+            )
+        }
+        Preset::Stirling => {
+            let second = isotherm(volume / ratio, cold);
+            (
+                [
+                    first,
+                    second,
+                    isotherm(second.0, hot),
+                    isotherm(volume, hot),
+                ],
+                [
+                    Process::Isothermal,
+                    Process::Isochoric,
+                    Process::Isothermal,
+                    Process::Isochoric,
+                ],
+            )
+        }
+        Preset::Brayton => {
+            let second = adiabat(
+                first,
+                find_volume(
+                    |volume| adiabat(first, volume).1,
+                    first.1 * ratio,
+                    low,
+                    high,
+                ),
+            );
+            if tempurature(second) >= hot {
+                return Err("T hot must be above T after compression");
+            }
+            let third = (
+                find_volume(|volume| tempurature((volume, second.1)), hot, low, high),
+                second.1,
+            );
+            (
+                [
+                    first,
+                    second,
+                    third,
+                    adiabat(
+                        third,
+                        find_volume(|volume| adiabat(third, volume).1, first.1, low, high),
+                    ),
+                ],
+                [
+                    Process::Adiabatic,
+                    Process::Isobaric,
+                    Process::Adiabatic,
+                    Process::Isobaric,
+                ],
+            )
+        }
+        Preset::Ericsson => {
+            let second = (
+                find_volume(
+                    |volume| gas_model.get_pressure(volume, cold),
+                    first.1 * ratio,
+                    low,
+                    high,
+                ),
+                first.1 * ratio,
+            );
+            let third = (
+                find_volume(|volume| tempurature((volume, second.1)), hot, low, high),
+                second.1,
+            );
+            (
+                [
+                    first,
+                    second,
+                    third,
+                    (
+                        find_volume(
+                            |volume| gas_model.get_pressure(volume, hot),
+                            first.1,
+                            low,
+                            high,
+                        ),
+                        first.1,
+                    ),
+                ],
+                [
+                    Process::Isothermal,
+                    Process::Isobaric,
+                    Process::Isothermal,
+                    Process::Isobaric,
+                ],
+            )
+        }
+    };
+    // below its critical tempurature a van der Waals isotherm dips under zero
+    if states.iter().any(|state| state.1 <= 0.) {
+        return Err("T cold is too low for the gas");
+    }
+    Ok((0..states.len())
+        .map(|i| {
+            let (volume, pressure) = states[i];
+            let (new_volume, new_pressure) = states[(i + 1) % states.len()];
+            Leg {
+                process: processes[i],
+                volume,
+                pressure,
+                new_volume,
+                new_pressure,
+            }
+        })
+        .collect())
+}
+
+// The legs of `preset` shrunk until its widest state sits at the largest volume
+// in `high`, or an error if any state then falls outside of `low` to `high`,
+// each as (m^3, Pa).
+pub fn fit(
+    preset: Preset,
+    gas_model: &dyn EquationOfState,
+    parameters: &Parameters,
+    low: (Scalar, Scalar),
+    high: (Scalar, Scalar),
+) -> Result<Vec<Leg>, &'static str> {
+    let mut volume = high.0;
+    let mut legs = Err("");
+    for _ in 0..FIT_STEPS {
+        legs = build(preset, gas_model, parameters, volume);
+        let Ok(legs) = &legs else {
+            break;
+        };
+        let widest = legs.iter().map(|leg| leg.volume).fold(0., Scalar::max);
+        if (widest / high.0 - 1.).abs() < FIT_TOLERANCE {
+            break;
+        }
+        volume *= high.0 / widest;
+    }
+    let contains = |low: Scalar, high: Scalar, value: Scalar| {
+        low * (1. - FIT_TOLERANCE) <= value && value <= high * (1. + FIT_TOLERANCE)
+    };
+    legs.and_then(|legs| {
+        if legs
+            .iter()
+            .all(|leg| contains(low.0, high.0, leg.volume) && contains(low.1, high.1, leg.pressure))
+        {
+            Ok(legs)
+        } else {
+            Err("does not fit on the plot")
+        }
+    })
+}
+
+// m^3, where f, which only rises or only falls with volume, reaches `target`
+fn find_volume(f: impl Fn(Scalar) -> Scalar, target: Scalar, low: Scalar, high: Scalar) -> Scalar {
+    let rising = f(high) > f(low);
+    let (mut low, mut high) = (low, high);
+    for _ in 0..SEARCH_STEPS {
+        let middle = (low + high) / 2.;
+        if (f(middle) < target) == rising {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        process,
+        thermo::{Gas, Model},
+    };
+
+    const VOLUME: Scalar = 60.;
+
+    // (J, J), work done and heat taken in over the whole cycle
+    fn get_work_and_heat(gas_model: &dyn EquationOfState, legs: &[Leg]) -> (Scalar, Scalar) {
+        legs.iter().fold((0., 0.), |(work, heat), leg| {
+            let transfer = process::get_transfer(
+                gas_model,
+                leg.process,
+                leg.volume,
+                leg.pressure,
+                leg.new_volume,
+                leg.new_pressure,
+            );
+            (work + transfer.work, heat + transfer.heat_absorbed)
+        })
+    }
+
+    #[test]
+    fn legs_close_and_follow_their_processes() {
+        // methane's low gamma can't reach T hot within the default compression,
+        // and with van der Waals its isotherm at T cold falls below zero
+        let failures = [
+            (
+                (Model::Ideal, Gas::METHANE, Preset::Carnot),
+                "compression too small for the temperatures",
+            ),
+            (
+                (Model::VanDerWaals, Gas::METHANE, Preset::Stirling),
+                "T cold is too low for the gas",
+            ),
+        ];
+        for model in [Model::Ideal, Model::VanDerWaals] {
+            for gas in [Gas::HELIUM, Gas::NITROGEN, Gas::METHANE] {
+                let gas_model = model.build(gas, 1.);
+                for preset in Preset::ALL {
+                    let legs = build(
+                        preset,
+                        gas_model.as_ref(),
+                        &preset.default_parameters(),
+                        VOLUME,
+                    );
+                    if let Some((_, error)) = failures
+                        .iter()
+                        .find(|(case, _)| *case == (model, gas, preset))
+                    {
+                        assert_eq!(legs, Err(*error));
+                        continue;
+                    }
+                    let legs = legs.unwrap_or_else(|error| {
+                        panic!("{:?} {} {}: {}", model, gas.name, preset.name(), error)
+                    });
+                    assert_eq!(legs.len(), 4);
+                    for (leg, next) in legs.iter().zip(legs.iter().cycle().skip(1)) {
+                        assert_eq!(
+                            (leg.new_volume, leg.new_pressure),
+                            (next.volume, next.pressure)
+                        );
+                        let on_curve = match leg.process {
+                            Process::Isochoric => leg.new_volume == leg.volume,
+                            process => {
+                                let pressure = process
+                                    .get_pressure(
+                                        gas_model.as_ref(),
+                                        leg.volume,
+                                        leg.pressure,
+                                        leg.new_volume,
+                                    )
+                                    .unwrap();
+                                (pressure - leg.new_pressure).abs() < 1e-3 * leg.new_pressure
+                            }
+                        };
+                        assert!(on_curve, "{} leg {:?} is off its curve", preset.name(), leg);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn default_presets_fit_the_plot() {
+        // the reachable states on the plot in main.rs, one handle radius in
+        // from its edges
+        let (margin, high) = (1.6, (79.2, 23.4));
+        // with van der Waals, helium's Otto peaks above the plot, and
+        // nitrogen's Brayton is wider than the plot at any size
+        let failures = [
+            (Model::VanDerWaals, Gas::HELIUM, Preset::Otto),
+            (Model::VanDerWaals, Gas::NITROGEN, Preset::Brayton),
+        ];
+        for model in [Model::Ideal, Model::VanDerWaals] {
+            for gas in [Gas::HELIUM, Gas::NITROGEN] {
+                let gas_model = model.build(gas, 1.);
+                let low = (gas_model.get_min_volume().max(0.) + margin, margin);
+                for preset in Preset::ALL {
+                    let legs = fit(
+                        preset,
+                        gas_model.as_ref(),
+                        &preset.default_parameters(),
+                        low,
+                        high,
+                    );
+                    if failures.contains(&(model, gas, preset)) {
+                        assert_eq!(legs, Err("does not fit on the plot"));
+                        continue;
+                    }
+                    let legs = legs.unwrap_or_else(|error| {
+                        panic!("{:?} {} {}: {}", model, gas.name, preset.name(), error)
+                    });
+                    let widest = legs.iter().map(|leg| leg.volume).fold(0., Scalar::max);
+                    assert!((widest / high.0 - 1.).abs() < FIT_TOLERANCE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ideal_presets_build() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        for preset in Preset::ALL {
+            assert!(
+                build(
+                    preset,
+                    gas_model.as_ref(),
+                    &preset.default_parameters(),
+                    VOLUME
+                )
+                .is_ok(),
+                "{}",
+                preset.name()
+            );
+        }
+    }
+
+    #[test]
+    fn carnot_efficiency() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let parameters = Preset::Carnot.default_parameters();
+        let legs = build(Preset::Carnot, gas_model.as_ref(), &parameters, VOLUME).unwrap();
+        let (work, heat) = get_work_and_heat(gas_model.as_ref(), &legs);
+        let expected = 1. - parameters.cold_tempurature / parameters.hot_tempurature;
+        assert!((work / heat - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn otto_efficiency() {
+        let gas_model = Model::Ideal.build(Gas::NITROGEN, 1.);
+        let parameters = Preset::Otto.default_parameters();
+        let legs = build(Preset::Otto, gas_model.as_ref(), &parameters, VOLUME).unwrap();
+        let (work, heat) = get_work_and_heat(gas_model.as_ref(), &legs);
+        let expected = 1.
+            - parameters
+                .compression_ratio
+                .powf(1. - Gas::NITROGEN.gamma());
+        assert!((work / heat - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bad_parameters_are_errors() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let build = |preset: Preset, change: &dyn Fn(&mut Parameters)| {
+            let mut parameters = preset.default_parameters();
+            change(&mut parameters);
+            build(preset, gas_model.as_ref(), &parameters, VOLUME)
+        };
+        assert_eq!(
+            build(Preset::Otto, &|parameters| parameters.compression_ratio =
+                1.),
+            Err("compression must be above 1")
+        );
+        assert_eq!(
+            build(Preset::Stirling, &|parameters| parameters.hot_tempurature =
+                parameters.cold_tempurature),
+            Err("T hot must be above T cold")
+        );
+        assert_eq!(
+            build(Preset::Diesel, &|parameters| parameters.cutoff_ratio = 1.),
+            Err("cutoff must be between 1 and compression")
+        );
+        assert_eq!(
+            build(Preset::Diesel, &|parameters| parameters.cutoff_ratio =
+                parameters.compression_ratio),
+            Err("cutoff must be between 1 and compression")
+        );
+        assert_eq!(
+            build(Preset::Carnot, &|parameters| parameters.compression_ratio =
+                1.5),
+            Err("compression too small for the temperatures")
+        );
+        assert_eq!(
+            build(Preset::Otto, &|parameters| parameters.hot_tempurature =
+                parameters.cold_tempurature + 1.),
+            Err("T hot must be above T after compression")
+        );
+    }
+}
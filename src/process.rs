@@ -0,0 +1,309 @@
+use crate::thermo::{self, EquationOfState, Scalar};
+
+// a move is split at least 2^HEAT_MIN_DEPTH times when looking for the points
+// where heat stops flowing in and starts flowing out
+const HEAT_MIN_DEPTH: u32 = 4;
+const HEAT_MAX_DEPTH: u32 = 12;
+const HEAT_TOLERANCE: Scalar = 1e-4;
+const HEATED_STATE_STEPS: u32 = 32;
+
+// What a move along a process exchanges with the surroundings.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Transfer {
+    pub work: Scalar,          // J, done by the gas
+    pub heat: Scalar,          // J, absorbed by the gas
+    pub heat_absorbed: Scalar, // J, the part of the move where heat flowed in
+}
+
+impl Transfer {
+    // J, the part of the move where heat flowed out, counted positive
+    pub fn heat_rejected(&self) -> Scalar {
+        self.heat_absorbed - self.heat
+    }
+}
+
+// The path the gas takes between two states.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Process {
+    Line,
+    Isothermal,
+    Isobaric,
+    Isochoric,
+    Adiabatic,
+    Polytropic(Scalar), // P V^n constant, with the exponent n
+}
+
+impl Process {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Process::Line => "free",
+            Process::Isothermal => "isothermal",
+            Process::Isobaric => "isobaric",
+            Process::Isochoric => "isochoric",
+            Process::Adiabatic => "adiabatic",
+            Process::Polytropic(_) => "polytropic",
+        }
+    }
+
+    // Pa, pressure at `new_volume` on this process's curve through (volume,
+    // pressure), if the curve can be written as a function of volume
+    pub fn get_pressure(
+        &self,
+        gas_model: &dyn EquationOfState,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Option<Scalar> {
+        match self {
+            Process::Line | Process::Isochoric => None,
+            Process::Isothermal => {
+                Some(gas_model.get_isothermal_pressure(volume, pressure, new_volume))
+            }
+            Process::Isobaric => Some(pressure),
+            Process::Adiabatic => {
+                Some(gas_model.get_adiabatic_pressure(volume, pressure, new_volume))
+            }
+            Process::Polytropic(exponent) => Some(pressure * (volume / new_volume).powf(*exponent)),
+        }
+    }
+}
+
+// (m^3, Pa), the state a fraction t of the way along `process`
+pub fn get_state(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    volume: Scalar,
+    pressure: Scalar,
+    new_volume: Scalar,
+    new_pressure: Scalar,
+    t: Scalar,
+) -> (Scalar, Scalar) {
+    let volume_t = volume + (new_volume - volume) * t;
+    match process {
+        Process::Line => (volume_t, pressure + (new_pressure - pressure) * t),
+        Process::Isochoric => (volume, pressure + (new_pressure - pressure) * t),
+        process => (
+            volume_t,
+            process
+                .get_pressure(gas_model, volume, pressure, volume_t)
+                .unwrap_or(pressure),
+        ),
+    }
+}
+
+// J, work done by the gas going from (volume, pressure) to (new_volume,
+// new_pressure) along `process`
+pub fn get_work(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    volume: Scalar,
+    pressure: Scalar,
+    new_volume: Scalar,
+    new_pressure: Scalar,
+) -> Scalar {
+    match process {
+        Process::Line => thermo::get_work(volume, pressure, new_volume, new_pressure),
+        Process::Isothermal => gas_model.get_isothermal_work(volume, pressure, new_volume),
+        Process::Isobaric => pressure * (new_volume - volume),
+        Process::Isochoric => 0.,
+        Process::Adiabatic => {
+            gas_model.get_energy(volume, pressure) - gas_model.get_energy(new_volume, new_pressure)
+        }
+        Process::Polytropic(exponent) => {
+            if (exponent - 1.).abs() < 1e-6 {
+                pressure * volume * (new_volume / volume).ln()
+            } else {
+                // (P2 V2 - P1 V1) / (1 - n), with P2 back on the curve
+                pressure * volume * ((volume / new_volume).powf(exponent - 1.) - 1.)
+                    / (1. - exponent)
+            }
+        }
+    }
+}
+
+// J, heat absorbed by the gas over the same move, from the first law
+pub fn get_heat(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    volume: Scalar,
+    pressure: Scalar,
+    new_volume: Scalar,
+    new_pressure: Scalar,
+) -> Scalar {
+    match process {
+        Process::Adiabatic => 0.,
+        _ => {
+            gas_model.get_energy(new_volume, new_pressure) - gas_model.get_energy(volume, pressure)
+                + get_work(
+                    gas_model,
+                    process,
+                    volume,
+                    pressure,
+                    new_volume,
+                    new_pressure,
+                )
+        }
+    }
+}
+
+// Work and heat for a whole move. Both are exact for the process; the move is
+// only subdivided to find how much of the heat flowed in and how much out.
+pub fn get_transfer(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    volume: Scalar,
+    pressure: Scalar,
+    new_volume: Scalar,
+    new_pressure: Scalar,
+) -> Transfer {
+    let heat = get_heat(
+        gas_model,
+        process,
+        volume,
+        pressure,
+        new_volume,
+        new_pressure,
+    );
+    Transfer {
+        work: get_work(
+            gas_model,
+            process,
+            volume,
+            pressure,
+            new_volume,
+            new_pressure,
+        ),
+        heat,
+        heat_absorbed: get_heat_absorbed(
+            gas_model,
+            process,
+            (volume, pressure),
+            (new_volume, new_pressure),
+            heat,
+            0,
+        ),
+    }
+}
+
+fn get_heat_absorbed(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    state: (Scalar, Scalar),
+    new_state: (Scalar, Scalar),
+    heat: Scalar,
+    depth: u32,
+) -> Scalar {
+    let middle = get_state(
+        gas_model,
+        process,
+        state.0,
+        state.1,
+        new_state.0,
+        new_state.1,
+        0.5,
+    );
+    let first = get_heat(gas_model, process, state.0, state.1, middle.0, middle.1);
+    let second = heat - first;
+    // the halves only disagree in sign if heat changed direction in between
+    let mixed = first.abs() + second.abs() - heat.abs()
+        > HEAT_TOLERANCE * (first.abs() + second.abs()).max(1.);
+    if depth >= HEAT_MAX_DEPTH || (depth >= HEAT_MIN_DEPTH && !mixed) {
+        heat.max(0.)
+    } else {
+        get_heat_absorbed(gas_model, process, state, middle, first, depth + 1)
+            + get_heat_absorbed(gas_model, process, middle, new_state, second, depth + 1)
+    }
+}
+
+// (m^3, Pa), the state reached by adding `heat` to (volume, pressure) along
+// an isochore or isobar. The heat grows with the pressure or volume that is
+// free to change, so it is bisected for between `low` and `high` of that.
+pub fn get_heated_state(
+    gas_model: &dyn EquationOfState,
+    process: Process,
+    volume: Scalar,
+    pressure: Scalar,
+    heat: Scalar,
+    low: Scalar,
+    high: Scalar,
+) -> Option<(Scalar, Scalar)> {
+    let get_state = |x: Scalar| match process {
+        Process::Isochoric => Some((volume, x)),
+        Process::Isobaric => Some((x, pressure)),
+        _ => None,
+    };
+    get_state(low)?;
+    let (mut low, mut high) = (low, high);
+    for _ in 0..HEATED_STATE_STEPS {
+        let middle = (low + high) / 2.;
+        let (new_volume, new_pressure) = get_state(middle)?;
+        if get_heat(
+            gas_model,
+            process,
+            volume,
+            pressure,
+            new_volume,
+            new_pressure,
+        ) < heat
+        {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    get_state((low + high) / 2.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thermo::{Gas, Model};
+
+    #[test]
+    fn adiabatic_exchanges_no_heat() {
+        for model in [Model::Ideal, Model::VanDerWaals] {
+            let gas_model = model.build(Gas::NITROGEN, 1.);
+            let (volume, pressure) = (40., 12.5);
+            let new_pressure = gas_model.get_adiabatic_pressure(volume, pressure, 20.);
+            let transfer = get_transfer(
+                gas_model.as_ref(),
+                Process::Adiabatic,
+                volume,
+                pressure,
+                20.,
+                new_pressure,
+            );
+            assert_eq!(transfer.heat, 0.);
+            assert_eq!(transfer.heat_absorbed, 0.);
+            // the work comes out of the internal energy
+            let energy_change =
+                gas_model.get_energy(20., new_pressure) - gas_model.get_energy(volume, pressure);
+            assert!((transfer.work + energy_change).abs() < 1e-3 * energy_change.abs());
+        }
+    }
+
+    #[test]
+    fn heat_is_split_where_it_changes_sign() {
+        // for a monatomic ideal gas on the line P = 20 - 0.375 (V - 20), heat
+        // flows in at 68.75 - 1.5 V J m^-3, so in until V = 45.8 and out after
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let transfer = get_transfer(gas_model.as_ref(), Process::Line, 20., 20., 60., 5.);
+        let turn = 68.75 / 1.5;
+        let absorbed = (68.75 - 1.5 * 20.) * (turn - 20.) / 2.;
+        let rejected = (1.5 * 60. - 68.75) * (60. - turn) / 2.;
+        assert!((transfer.heat_absorbed - absorbed).abs() < 1e-2 * absorbed);
+        assert!((transfer.heat_rejected() - rejected).abs() < 1e-2 * rejected);
+        assert!((transfer.heat - (absorbed - rejected)).abs() < 1e-2 * absorbed);
+    }
+
+    #[test]
+    fn heat_in_one_direction_is_not_split() {
+        let gas_model = Model::Ideal.build(Gas::HELIUM, 1.);
+        let heating = get_transfer(gas_model.as_ref(), Process::Isochoric, 40., 5., 40., 15.);
+        assert!(heating.heat > 0.);
+        assert_eq!(heating.heat_absorbed, heating.heat);
+        let cooling = get_transfer(gas_model.as_ref(), Process::Isobaric, 60., 10., 30., 10.);
+        assert!(cooling.heat < 0.);
+        assert_eq!(cooling.heat_absorbed, 0.);
+    }
+}
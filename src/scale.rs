@@ -0,0 +1,135 @@
+use crate::thermo::{Scalar, R};
+
+// How many directions of motion the particles' speeds stand for. The particles
+// only move in the plane, but their speeds can be those of a gas moving in
+// three dimensions, which is what the equations of state describe.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dimensions {
+    Two,
+    Three,
+}
+
+impl Dimensions {
+    pub fn count(&self) -> Scalar {
+        match self {
+            Dimensions::Two => 2.,
+            Dimensions::Three => 3.,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dimensions::Two => "2D",
+            Dimensions::Three => "3D",
+        }
+    }
+}
+
+// Maps the simulated particles, which move in a plane measured in pixels, onto
+// the gas whose state is on the plot. Each particle stands for an equal share
+// of the moles, and is much heavier than the molecules it replaces so that it
+// moves slowly enough to watch.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ParticleScale {
+    pub dimensions: Dimensions,
+    pub length: Scalar, // m per px
+    pub depth: Scalar,  // m, how far the box reaches out of the plane
+    pub mass: Scalar,   // kg, of one particle
+    pub particles: Scalar,
+    pub moles: Scalar,
+}
+
+impl ParticleScale {
+    // m^3, of a region of the plane `width` by `height` px
+    pub fn get_volume(&self, width: Scalar, height: Scalar) -> Scalar {
+        width * height * self.length.powi(2) * self.depth
+    }
+
+    // px, how wide a region `height` px tall is to hold `volume`
+    pub fn get_width(&self, volume: Scalar, height: Scalar) -> Scalar {
+        volume / (height * self.length.powi(2) * self.depth)
+    }
+
+    // J, the particles' total kinetic energy at `tempurature`, with kT/2 per
+    // particle for each of the dimensions, where k is the particle's share of nR
+    pub fn get_kinetic_energy(&self, tempurature: Scalar) -> Scalar {
+        self.dimensions.count() / 2. * self.moles * R * tempurature
+    }
+
+    // K, the kinetic tempurature of particles with `kinetic_energy` in total
+    pub fn get_tempurature(&self, kinetic_energy: Scalar) -> Scalar {
+        2. * kinetic_energy / (self.dimensions.count() * self.moles * R)
+    }
+
+    // J, of one particle moving at `speed` px s^-1
+    pub fn get_particle_energy(&self, speed: Scalar) -> Scalar {
+        self.mass * (speed * self.length).powi(2) / 2.
+    }
+
+    // px s^-1, the particles' root mean square speed along one direction of
+    // the plane at `tempurature`
+    pub fn get_thermal_speed(&self, tempurature: Scalar) -> Scalar {
+        (self.get_kinetic_energy(tempurature) / (self.particles * self.mass)).sqrt() / self.length
+    }
+
+    // Pa, from `impulse` kg px s^-1 handed over `time` s to `perimeter` px of
+    // wall. In the plane the force per length times the area is the particles'
+    // kinetic energy, while in the box PV is 2/dimensions of it.
+    pub fn get_pressure(&self, impulse: Scalar, time: Scalar, perimeter: Scalar) -> Scalar {
+        2. / self.dimensions.count() * impulse / (time * perimeter * self.depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_scale(dimensions: Dimensions) -> ParticleScale {
+        ParticleScale {
+            dimensions,
+            length: 0.01,
+            depth: 25.,
+            mass: 10.,
+            particles: 100.,
+            moles: 2.,
+        }
+    }
+
+    #[test]
+    fn width_undoes_volume() {
+        let scale = get_scale(Dimensions::Two);
+        let (width, height) = (300., 240.);
+        let volume = scale.get_volume(width, height);
+        assert!((scale.get_width(volume, height) - width).abs() < 1e-3);
+    }
+
+    #[test]
+    fn tempurature_undoes_kinetic_energy() {
+        for dimensions in [Dimensions::Two, Dimensions::Three] {
+            let scale = get_scale(dimensions);
+            let tempurature = 45.;
+            let kinetic_energy = scale.get_kinetic_energy(tempurature);
+            assert!((scale.get_tempurature(kinetic_energy) - tempurature).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn wall_impulse_gives_ideal_pressure() {
+        for dimensions in [Dimensions::Two, Dimensions::Three] {
+            let scale = get_scale(dimensions);
+            let (width, height, tempurature) = (300., 240., 45.);
+            // in the plane the force per length of wall times the area is the
+            // kinetic energy, all in px
+            let kinetic_energy = scale.get_kinetic_energy(tempurature) / scale.length.powi(2);
+            let (time, perimeter) = (4., 2. * (width + height));
+            let impulse = kinetic_energy / (width * height) * time * perimeter;
+            let volume = scale.get_volume(width, height);
+            let pressure = scale.moles * R * tempurature / volume;
+            assert!(
+                (scale.get_pressure(impulse, time, perimeter) - pressure).abs() < 1e-4 * pressure,
+                "{}",
+                dimensions.name()
+            );
+        }
+    }
+}
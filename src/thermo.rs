@@ -0,0 +1,454 @@
+// State math for the gas in physical units only. Nothing in here knows about
+// pixels, the plot or Bevy; see the mapping functions in main.rs for that.
+
+pub type Scalar = f32;
+
+pub const R: Scalar = 8.314; // J mol^-1 K^-1
+
+// The plot spans tens of m^3 and Pa, so the real van der Waals constants are
+// scaled by these factors to put each gas's critical point on screen.
+const VDW_VOLUME_SCALE: Scalar = 1.5e5;
+const VDW_PRESSURE_SCALE: Scalar = 1.5e-6;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Gas {
+    pub name: &'static str,
+    pub degrees_of_freedom: Scalar,
+    pub a: Scalar, // Pa m^6 mol^-2
+    pub b: Scalar, // m^3 mol^-1
+}
+
+impl Gas {
+    pub const HELIUM: Gas = Gas {
+        name: "helium",
+        degrees_of_freedom: 3.,
+        a: 3.46e-3 * VDW_PRESSURE_SCALE * VDW_VOLUME_SCALE * VDW_VOLUME_SCALE,
+        b: 2.38e-5 * VDW_VOLUME_SCALE,
+    };
+    pub const NITROGEN: Gas = Gas {
+        name: "nitrogen",
+        degrees_of_freedom: 5.,
+        a: 0.137 * VDW_PRESSURE_SCALE * VDW_VOLUME_SCALE * VDW_VOLUME_SCALE,
+        b: 3.87e-5 * VDW_VOLUME_SCALE,
+    };
+    pub const METHANE: Gas = Gas {
+        name: "methane",
+        degrees_of_freedom: 6.,
+        a: 0.2283 * VDW_PRESSURE_SCALE * VDW_VOLUME_SCALE * VDW_VOLUME_SCALE,
+        b: 4.278e-5 * VDW_VOLUME_SCALE,
+    };
+
+    pub fn kind(&self) -> &'static str {
+        if self.degrees_of_freedom == 3. {
+            "monatomic"
+        } else if self.degrees_of_freedom == 5. {
+            "diatomic"
+        } else if self.degrees_of_freedom == 6. {
+            "polyatomic"
+        } else {
+            "custom"
+        }
+    }
+
+    // J mol^-1 K^-1
+    pub fn cv(&self) -> Scalar {
+        self.degrees_of_freedom / 2. * R
+    }
+
+    // J mol^-1 K^-1
+    pub fn cp(&self) -> Scalar {
+        self.cv() + R
+    }
+
+    pub fn gamma(&self) -> Scalar {
+        self.cp() / self.cv()
+    }
+
+    // J mol^-1 K^-1, along P V^n = const for an ideal gas only. It is negative
+    // for 1 < n < gamma, where the gas cools even as heat flows in, and
+    // infinite at n = 1, where the process is isothermal.
+    pub fn cn(&self, exponent: Scalar) -> Scalar {
+        self.cv() - R / (exponent - 1.)
+    }
+}
+
+// Everything the app needs to know about a gas model. The isotherm through a
+// state follows from T and P, but the adiabat depends on how the model stores
+// energy, so every model has to supply its own.
+pub trait EquationOfState: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // K
+    fn get_tempurature(&self, volume: Scalar, pressure: Scalar) -> Scalar;
+
+    // Pa
+    fn get_pressure(&self, volume: Scalar, tempurature: Scalar) -> Scalar;
+
+    // J
+    fn get_energy(&self, volume: Scalar, pressure: Scalar) -> Scalar;
+
+    // J
+    fn get_enthalpy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        self.get_energy(volume, pressure) + pressure * volume
+    }
+
+    // J K^-1, relative to 1 K and 1 m^3 of free volume
+    fn get_entropy(&self, volume: Scalar, pressure: Scalar) -> Scalar;
+
+    // J, kinetic part of the internal energy, which is what the particles carry
+    fn get_thermal_energy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        self.get_energy(volume, pressure)
+    }
+
+    // m^3, volume taken up by the molecules themselves
+    fn get_min_volume(&self) -> Scalar {
+        0.
+    }
+
+    // Pa, pressure at `new_volume` on the isotherm through (volume, pressure)
+    fn get_isothermal_pressure(
+        &self,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Scalar {
+        self.get_pressure(new_volume, self.get_tempurature(volume, pressure))
+    }
+
+    // Pa, pressure at `new_volume` on the adiabat through (volume, pressure)
+    fn get_adiabatic_pressure(
+        &self,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Scalar;
+
+    // J, work done by the gas along the isotherm through (volume, pressure)
+    fn get_isothermal_work(&self, volume: Scalar, pressure: Scalar, new_volume: Scalar) -> Scalar {
+        integrate(
+            &|volume_t| self.get_isothermal_pressure(volume, pressure, volume_t),
+            volume,
+            new_volume,
+        )
+    }
+}
+
+pub struct IdealGas {
+    pub gas: Gas,
+    pub moles: Scalar,
+}
+
+impl EquationOfState for IdealGas {
+    fn name(&self) -> &'static str {
+        "ideal gas"
+    }
+
+    fn get_tempurature(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        volume * pressure / (self.moles * R)
+    }
+
+    fn get_pressure(&self, volume: Scalar, tempurature: Scalar) -> Scalar {
+        self.moles * R * tempurature / volume
+    }
+
+    fn get_energy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        self.moles * self.gas.cv() * self.get_tempurature(volume, pressure)
+    }
+
+    fn get_entropy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        // n Cv ln T + n R ln V
+        self.moles * (self.gas.cv() * self.get_tempurature(volume, pressure).ln() + R * volume.ln())
+    }
+
+    fn get_isothermal_pressure(
+        &self,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Scalar {
+        pressure * volume / new_volume
+    }
+
+    fn get_adiabatic_pressure(
+        &self,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Scalar {
+        pressure * volume.powf(self.gas.gamma()) / new_volume.powf(self.gas.gamma())
+    }
+
+    fn get_isothermal_work(&self, volume: Scalar, pressure: Scalar, new_volume: Scalar) -> Scalar {
+        // nRT ln(V2 / V1)
+        pressure * volume * (new_volume / volume).ln()
+    }
+}
+
+pub struct VanDerWaals {
+    pub gas: Gas,
+    pub moles: Scalar,
+}
+
+impl EquationOfState for VanDerWaals {
+    fn name(&self) -> &'static str {
+        "van der Waals"
+    }
+
+    fn get_tempurature(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        (pressure + self.gas.a * self.moles * self.moles / (volume * volume))
+            * (volume - self.moles * self.gas.b)
+            / (self.moles * R)
+    }
+
+    fn get_pressure(&self, volume: Scalar, tempurature: Scalar) -> Scalar {
+        self.moles * R * tempurature / (volume - self.moles * self.gas.b)
+            - self.gas.a * self.moles * self.moles / (volume * volume)
+    }
+
+    fn get_energy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        self.get_thermal_energy(volume, pressure) - self.gas.a * self.moles * self.moles / volume
+    }
+
+    fn get_entropy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        // n Cv ln T + n R ln(V - nb)
+        self.moles
+            * (self.gas.cv() * self.get_tempurature(volume, pressure).ln()
+                + R * (volume - self.moles * self.gas.b).ln())
+    }
+
+    fn get_thermal_energy(&self, volume: Scalar, pressure: Scalar) -> Scalar {
+        self.moles * self.gas.cv() * self.get_tempurature(volume, pressure)
+    }
+
+    fn get_min_volume(&self) -> Scalar {
+        self.moles * self.gas.b
+    }
+
+    fn get_adiabatic_pressure(
+        &self,
+        volume: Scalar,
+        pressure: Scalar,
+        new_volume: Scalar,
+    ) -> Scalar {
+        // T (V - nb)^(R / Cv) is constant along the adiabat
+        let new_tempurature = self.get_tempurature(volume, pressure)
+            * ((volume - self.moles * self.gas.b) / (new_volume - self.moles * self.gas.b))
+                .powf(R / self.gas.cv());
+        self.get_pressure(new_volume, new_tempurature)
+    }
+
+    fn get_isothermal_work(&self, volume: Scalar, pressure: Scalar, new_volume: Scalar) -> Scalar {
+        // nRT ln((V2 - nb) / (V1 - nb)) + a n^2 (1 / V2 - 1 / V1)
+        self.moles
+            * R
+            * self.get_tempurature(volume, pressure)
+            * ((new_volume - self.moles * self.gas.b) / (volume - self.moles * self.gas.b)).ln()
+            + self.gas.a * self.moles * self.moles * (1. / new_volume - 1. / volume)
+    }
+}
+
+// A state function that can go on a plot axis.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Quantity {
+    Pressure,
+    Volume,
+    Tempurature,
+    Energy,
+    Enthalpy,
+    Entropy,
+}
+
+impl Quantity {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Quantity::Pressure => "P",
+            Quantity::Volume => "V",
+            Quantity::Tempurature => "T",
+            Quantity::Energy => "U",
+            Quantity::Enthalpy => "H",
+            Quantity::Entropy => "S",
+        }
+    }
+
+    // whether zero means something, so plots should start their axis there
+    pub fn is_absolute(&self) -> bool {
+        matches!(
+            self,
+            Quantity::Pressure | Quantity::Volume | Quantity::Tempurature
+        )
+    }
+
+    pub fn get(&self, gas_model: &dyn EquationOfState, volume: Scalar, pressure: Scalar) -> Scalar {
+        match self {
+            Quantity::Pressure => pressure,
+            Quantity::Volume => volume,
+            Quantity::Tempurature => gas_model.get_tempurature(volume, pressure),
+            Quantity::Energy => gas_model.get_energy(volume, pressure),
+            Quantity::Enthalpy => gas_model.get_enthalpy(volume, pressure),
+            Quantity::Entropy => gas_model.get_entropy(volume, pressure),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Model {
+    Ideal,
+    VanDerWaals,
+}
+
+impl Model {
+    pub fn build(&self, gas: Gas, moles: Scalar) -> Box<dyn EquationOfState> {
+        match self {
+            Model::Ideal => Box::new(IdealGas { gas, moles }),
+            Model::VanDerWaals => Box::new(VanDerWaals { gas, moles }),
+        }
+    }
+}
+
+// J, work done by the gas moving in a straight line between two states
+pub fn get_work(
+    volume: Scalar,
+    pressure: Scalar,
+    new_volume: Scalar,
+    new_pressure: Scalar,
+) -> Scalar {
+    (pressure + new_pressure) * (new_volume - volume) / 2.
+}
+
+const INTEGRATE_TOLERANCE: Scalar = 1e-4;
+const INTEGRATE_MAX_DEPTH: u32 = 16;
+
+// integral of f from a to b by adaptive Simpson's rule
+pub fn integrate(f: &impl Fn(Scalar) -> Scalar, a: Scalar, b: Scalar) -> Scalar {
+    let (fa, fm, fb) = (f(a), f((a + b) / 2.), f(b));
+    let whole = (b - a) / 6. * (fa + 4. * fm + fb);
+    integrate_step(f, a, b, fa, fm, fb, whole, INTEGRATE_MAX_DEPTH)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn integrate_step(
+    f: &impl Fn(Scalar) -> Scalar,
+    a: Scalar,
+    b: Scalar,
+    fa: Scalar,
+    fm: Scalar,
+    fb: Scalar,
+    whole: Scalar,
+    depth: u32,
+) -> Scalar {
+    let m = (a + b) / 2.;
+    let (flm, frm) = (f((a + m) / 2.), f((m + b) / 2.));
+    let left = (m - a) / 6. * (fa + 4. * flm + fm);
+    let right = (b - m) / 6. * (fm + 4. * frm + fb);
+    if depth == 0 || (left + right - whole).abs() <= INTEGRATE_TOLERANCE * whole.abs().max(1.) {
+        left + right + (left + right - whole) / 15.
+    } else {
+        integrate_step(f, a, m, fa, flm, fm, left, depth - 1)
+            + integrate_step(f, m, b, fm, frm, fb, right, depth - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn models() -> [Box<dyn EquationOfState>; 4] {
+        [
+            Model::Ideal.build(Gas::HELIUM, 1.),
+            Model::Ideal.build(Gas::NITROGEN, 2.),
+            Model::VanDerWaals.build(Gas::HELIUM, 1.),
+            Model::VanDerWaals.build(Gas::METHANE, 2.),
+        ]
+    }
+
+    fn assert_close(actual: Scalar, expected: Scalar, tolerance: Scalar) {
+        assert!(
+            (actual - expected).abs() <= tolerance * expected.abs().max(1.),
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    #[test]
+    fn tempurature_and_pressure_round_trip() {
+        for gas_model in models() {
+            for (volume, pressure) in [(20., 5.), (40., 12.5), (70., 20.)] {
+                let tempurature = gas_model.get_tempurature(volume, pressure);
+                assert_close(gas_model.get_pressure(volume, tempurature), pressure, 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn energy_follows_tempurature() {
+        let gas = Gas::NITROGEN;
+        let ideal = IdealGas { gas, moles: 2. };
+        let (volume, pressure) = (30., 10.);
+        let tempurature = ideal.get_tempurature(volume, pressure);
+        assert_close(
+            ideal.get_energy(volume, pressure),
+            2. * gas.cv() * tempurature,
+            1e-5,
+        );
+
+        // the same T has the same kinetic part, with the attraction on top
+        let van_der_waals = VanDerWaals { gas, moles: 2. };
+        let pressure = van_der_waals.get_pressure(volume, tempurature);
+        assert_close(
+            van_der_waals.get_thermal_energy(volume, pressure),
+            2. * gas.cv() * tempurature,
+            1e-4,
+        );
+        assert_close(
+            van_der_waals.get_energy(volume, pressure),
+            2. * gas.cv() * tempurature - gas.a * 4. / volume,
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn entropy_is_constant_along_adiabat() {
+        for gas_model in models() {
+            let (volume, pressure) = (40., 12.5);
+            let entropy = gas_model.get_entropy(volume, pressure);
+            for new_volume in [15., 25., 60., 80.] {
+                let new_pressure = gas_model.get_adiabatic_pressure(volume, pressure, new_volume);
+                assert_close(
+                    gas_model.get_entropy(new_volume, new_pressure),
+                    entropy,
+                    1e-4,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn integrate_matches_isothermal_work() {
+        for gas_model in models() {
+            let (volume, pressure) = (40., 12.5);
+            for new_volume in [15., 75.] {
+                let integrated = integrate(
+                    &|volume_t| gas_model.get_isothermal_pressure(volume, pressure, volume_t),
+                    volume,
+                    new_volume,
+                );
+                assert_close(
+                    integrated,
+                    gas_model.get_isothermal_work(volume, pressure, new_volume),
+                    1e-3,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn min_volume_is_the_molecules_own() {
+        assert_eq!(Model::Ideal.build(Gas::HELIUM, 2.).get_min_volume(), 0.);
+        let gas_model = Model::VanDerWaals.build(Gas::METHANE, 2.);
+        assert_close(gas_model.get_min_volume(), 2. * Gas::METHANE.b, 1e-6);
+        // the pressure blows up as the free volume goes to zero
+        assert!(gas_model.get_pressure(gas_model.get_min_volume() * 1.001, 50.) > 1e3);
+    }
+}
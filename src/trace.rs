@@ -0,0 +1,27 @@
+use crate::{process::Process, thermo::Scalar};
+
+// One visited state, with the work and heat of the segment that led to it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TracePoint {
+    pub time: Scalar,          // s
+    pub volume: Scalar,        // m^3
+    pub pressure: Scalar,      // Pa
+    pub process: Process,      // how the segment was travelled
+    pub work: Scalar,          // J, done by the gas
+    pub heat: Scalar,          // J, absorbed by the gas
+    pub heat_absorbed: Scalar, // J, the part of the segment where heat flowed in
+}
+
+impl TracePoint {
+    pub fn start(time: Scalar, volume: Scalar, pressure: Scalar) -> TracePoint {
+        TracePoint {
+            time,
+            volume,
+            pressure,
+            process: Process::Line,
+            work: 0.,
+            heat: 0.,
+            heat_absorbed: 0.,
+        }
+    }
+}